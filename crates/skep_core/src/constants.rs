@@ -43,6 +43,7 @@ pub const CONF_COMMAND_STATE: &str = "command_state";
 pub const CONF_COMMAND_STOP: &str = "command_stop";
 pub const CONF_CONDITION: &str = "condition";
 pub const CONF_CONDITIONS: &str = "conditions";
+pub const CONF_CORE: &str = "core";
 pub const CONF_CONTINUE_ON_ERROR: &str = "continue_on_error";
 pub const CONF_CONTINUE_ON_TIMEOUT: &str = "continue_on_timeout";
 pub const CONF_COUNT: &str = "count";
@@ -211,6 +212,11 @@ pub const STATE_OPEN: &str = "open";
 pub const STATE_OPENING: &str = "opening";
 pub const STATE_CLOSED: &str = "closed";
 pub const STATE_CLOSING: &str = "closing";
+pub const STATE_LOCKED: &str = "locked";
+pub const STATE_UNLOCKED: &str = "unlocked";
+pub const STATE_LOCKING: &str = "locking";
+pub const STATE_UNLOCKING: &str = "unlocking";
+pub const STATE_JAMMED: &str = "jammed";
 pub const STATE_BUFFERING: &str = "buffering";
 pub const STATE_PLAYING: &str = "playing";
 pub const STATE_PAUSED: &str = "paused";
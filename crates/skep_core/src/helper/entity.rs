@@ -24,7 +24,7 @@ pub struct SkepEntityComponent {
     entity_picture: Option<String>,
     pub entity_registry_enabled_default: bool,
     entity_registry_visible_default: bool,
-    extra_state_attributes: HashMap<String, Value>,
+    pub extra_state_attributes: HashMap<String, Value>,
     force_update: bool,
     pub icon: Option<String>,
     pub name: Option<String>,
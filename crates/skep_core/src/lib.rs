@@ -6,8 +6,11 @@ use crate::{
     helper::event::SkepCoreEventPlugin,
     integration::Integration,
     loader::load_config_toml,
+    notify::SkepNotifyPlugin,
     platform::Platform,
     states::{SkepStatePlugin, State, StateAttributes},
+    tag::SkepTagPlugin,
+    zone::{load_home_zone, HomeZone},
 };
 use bevy_app::{App, Plugin, Startup};
 use bevy_ecs::{
@@ -28,10 +31,13 @@ pub mod entity;
 pub mod helper;
 pub mod integration;
 pub mod loader;
+pub mod notify;
 pub mod platform;
 pub mod states;
+pub mod tag;
 pub mod template;
 pub mod typing;
+pub mod zone;
 
 pub struct SkepCorePlugin;
 
@@ -42,6 +48,8 @@ impl Plugin for SkepCorePlugin {
             SkepDevicePlugin,
             SkepCoreEventPlugin,
             SkepStatePlugin,
+            SkepTagPlugin,
+            SkepNotifyPlugin,
         ))
         .register_type::<Integration>()
         .register_type::<Platform>()
@@ -50,7 +58,9 @@ impl Plugin for SkepCorePlugin {
         .register_type::<State>()
         .register_type::<StateAttributes>()
         .register_type::<EntityCategory>()
+        .register_type::<HomeZone>()
         .init_resource::<SkepResource>()
+        .observe(load_home_zone)
         // .register_type::<DeviceEntry>()
         .add_systems(Startup, load_config_toml);
     }
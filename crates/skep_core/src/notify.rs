@@ -0,0 +1,94 @@
+use crate::helper::entity::SkepEntityComponent;
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use log::warn;
+
+pub struct SkepNotifyPlugin;
+
+impl Plugin for SkepNotifyPlugin {
+    fn build(&self, app: &mut App) {
+        app.observe(on_send_notification);
+    }
+}
+
+/// Marks an entity of any integration that can receive a [`Notify`].
+#[derive(Debug, Component, Default)]
+pub struct NotifyEntity;
+
+/// Notification service, fired into the world and routed to the [`NotifyEntity`] with the
+/// given entity id.
+#[derive(Debug, Event, Clone, PartialEq, Eq)]
+pub struct SendNotification {
+    pub entity_id: String,
+    pub message: String,
+    pub title: Option<String>,
+}
+
+/// A notification, triggered with the receiving [`NotifyEntity`] as target.
+#[derive(Debug, Event, Clone, PartialEq, Eq)]
+pub struct Notify {
+    pub message: String,
+    pub title: Option<String>,
+}
+
+fn on_send_notification(
+    trigger: Trigger<SendNotification>,
+    mut commands: Commands,
+    q_notify: Query<(Entity, &SkepEntityComponent), With<NotifyEntity>>,
+) {
+    let event = trigger.event();
+    let Some((entity, _)) = q_notify
+        .iter()
+        .find(|(_, skep_entity)| skep_entity.entity_id.as_deref() == Some(&event.entity_id))
+    else {
+        warn!("notify entity {} not found", event.entity_id);
+        return;
+    };
+    commands.trigger_targets(
+        Notify {
+            message: event.message.clone(),
+            title: event.title.clone(),
+        },
+        entity,
+    );
+}
+
+#[test]
+fn test_send_notification() {
+    #[derive(Resource, Default)]
+    struct Received(Vec<(Entity, Notify)>);
+
+    let mut app = App::new();
+    app.add_plugins(SkepNotifyPlugin)
+        .init_resource::<Received>()
+        .observe(|trigger: Trigger<Notify>, mut received: ResMut<Received>| {
+            received.0.push((trigger.entity(), trigger.event().clone()));
+        });
+    let skep_entity = |entity_id: &str| {
+        let mut skep_entity = SkepEntityComponent::default();
+        skep_entity.entity_id = Some(entity_id.to_string());
+        skep_entity
+    };
+    let display = app
+        .world_mut()
+        .spawn((skep_entity("notify.eink_display"), NotifyEntity))
+        .id();
+    app.world_mut().spawn(skep_entity("notify.alert_led"));
+
+    app.world_mut().trigger(SendNotification {
+        entity_id: "notify.eink_display".to_string(),
+        message: "Garbage day".to_string(),
+        title: None,
+    });
+    app.world_mut().trigger(SendNotification {
+        entity_id: "notify.alert_led".to_string(),
+        message: "ignored".to_string(),
+        title: None,
+    });
+    app.world_mut().flush();
+
+    let received = &app.world().resource::<Received>().0;
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].0, display);
+    assert_eq!(received[0].1.message, "Garbage day");
+}
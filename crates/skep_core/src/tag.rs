@@ -0,0 +1,74 @@
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_utils::HashMap;
+use chrono::{DateTime, Utc};
+
+pub(crate) struct SkepTagPlugin;
+
+impl Plugin for SkepTagPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TagRegistry>().observe(on_tag_scanned);
+    }
+}
+
+/// Fired into the world when a tag reader scans a tag.
+#[derive(Debug, Event, Clone, PartialEq, Eq)]
+pub struct TagScanned {
+    pub tag_id: String,
+    /// The [`Device`](crate::device::Device) of the reader that scanned the tag
+    pub device: Entity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagEntry {
+    pub last_scanned: DateTime<Utc>,
+    /// The device that last scanned the tag
+    pub device: Entity,
+}
+
+/// All tags scanned so far, keyed by tag id.
+#[derive(Debug, Resource, Default)]
+pub struct TagRegistry {
+    tags: HashMap<String, TagEntry>,
+}
+
+impl TagRegistry {
+    pub fn get(&self, tag_id: &str) -> Option<&TagEntry> {
+        self.tags.get(tag_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &TagEntry)> {
+        self.tags.iter()
+    }
+
+    pub fn scanned(&mut self, tag_id: impl Into<String>, device: Entity) {
+        self.tags.insert(
+            tag_id.into(),
+            TagEntry {
+                last_scanned: Utc::now(),
+                device,
+            },
+        );
+    }
+}
+
+fn on_tag_scanned(trigger: Trigger<TagScanned>, mut registry: ResMut<TagRegistry>) {
+    let event = trigger.event();
+    registry.scanned(event.tag_id.clone(), event.device);
+}
+
+#[test]
+fn test_tag_registry() {
+    let mut app = App::new();
+    app.add_plugins(SkepTagPlugin);
+    let device = app.world_mut().spawn_empty().id();
+
+    app.world_mut().trigger(TagScanned {
+        tag_id: "04:a2:5b:e2".to_string(),
+        device,
+    });
+
+    let registry = app.world().resource::<TagRegistry>();
+    assert_eq!(registry.get("04:a2:5b:e2").unwrap().device, device);
+    assert!(registry.get("unknown").is_none());
+}
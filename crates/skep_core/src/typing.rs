@@ -2,10 +2,11 @@ use bevy_ecs::event::Event;
 use chrono::{DateTime, NaiveDate, Utc};
 use either::Either;
 use serde_json::{Map, Value};
+use std::fmt::{Display, Formatter};
 
 pub type StateType = Option<Either<String, Either<i32, f64>>>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ValueType {
     String(String),
     Float(f64),
@@ -14,6 +15,18 @@ pub enum ValueType {
     Date(NaiveDate),
 }
 
+impl Display for ValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueType::String(value) => write!(f, "{}", value),
+            ValueType::Float(value) => write!(f, "{}", value),
+            ValueType::Int(value) => write!(f, "{}", value),
+            ValueType::DateTime(value) => write!(f, "{}", value.to_rfc3339()),
+            ValueType::Date(value) => write!(f, "{}", value.format("%Y-%m-%d")),
+        }
+    }
+}
+
 pub type ConfigType = Map<String, Value>;
 
 #[derive(Event, Debug, Clone)]
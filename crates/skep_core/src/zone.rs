@@ -0,0 +1,102 @@
+use crate::{constants::CONF_CORE, loader::LoadConfig};
+use bevy_ecs::{
+    observer::Trigger,
+    system::{Commands, Resource},
+};
+use bevy_reflect::Reflect;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// Mean earth radius in meters
+const EARTH_RADIUS: f64 = 6_371_000.0;
+const DEFAULT_RADIUS: f64 = 100.0;
+
+/// The home location, used to resolve `home`/`not_home` for GPS based trackers.
+#[derive(Debug, Resource, Clone, Reflect, Serialize, Deserialize)]
+pub struct HomeZone {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Radius of the zone in meters
+    #[serde(default = "default_radius")]
+    pub radius: f64,
+}
+
+fn default_radius() -> f64 {
+    DEFAULT_RADIUS
+}
+
+impl HomeZone {
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+            radius: DEFAULT_RADIUS,
+        }
+    }
+
+    /// Whether a position with the given accuracy in meters is within the zone.
+    pub fn contains(&self, latitude: f64, longitude: f64, accuracy: f64) -> bool {
+        distance(self.latitude, self.longitude, latitude, longitude) - accuracy < self.radius
+    }
+}
+
+/// Insert the [`HomeZone`] from the `latitude`, `longitude` and `radius` of the `[core]` config.
+pub(crate) fn load_home_zone(trigger: Trigger<LoadConfig>, mut commands: Commands) {
+    let Some(core_config) = trigger.event().config.get(CONF_CORE) else {
+        return;
+    };
+    match serde_json::from_value::<HomeZone>(core_config.clone()) {
+        Ok(home_zone) => commands.insert_resource(home_zone),
+        Err(e) => warn!("No home zone in the core config: {}", e),
+    }
+}
+
+/// Great-circle distance between two coordinates in meters.
+pub fn distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+#[test]
+fn test_home_zone() {
+    let zone = HomeZone::new(52.3731, 4.8922);
+
+    assert!(zone.contains(52.3731, 4.8922, 0.0));
+    // ~150m north of the zone center
+    assert!(!zone.contains(52.37445, 4.8922, 0.0));
+    assert!(zone.contains(52.37445, 4.8922, 60.0));
+    assert!((distance(52.3731, 4.8922, 51.9225, 4.4792) - 57_300.0).abs() < 500.0);
+}
+
+#[test]
+fn test_load_home_zone() {
+    use bevy_ecs::world::World;
+
+    let mut world = World::new();
+    world.observe(load_home_zone);
+    world.trigger(LoadConfig {
+        config: serde_json::json!({
+            "core": {
+                "latitude": 52.3731,
+                "longitude": 4.8922,
+                "radius": 250.0,
+                "time_zone": "Europe/Amsterdam"
+            }
+        }),
+    });
+    world.flush();
+    let home_zone = world.resource::<HomeZone>();
+    assert_eq!(home_zone.latitude, 52.3731);
+    assert_eq!(home_zone.radius, 250.0);
+
+    let mut world = World::new();
+    world.observe(load_home_zone);
+    world.trigger(LoadConfig {
+        config: serde_json::json!({"core": {"time_zone": "Europe/Amsterdam"}}),
+    });
+    world.flush();
+    assert!(world.get_resource::<HomeZone>().is_none());
+}
@@ -33,6 +33,7 @@ lazy_static! {
         m.insert("cmd_on_tpl", "command_on_template");
         m.insert("cmd_t", "command_topic");
         m.insert("cmd_tpl", "command_template");
+        m.insert("cmps", "components");
         m.insert("cod_arm_req", "code_arm_required");
         m.insert("cod_dis_req", "code_disarm_required");
         m.insert("cod_form", "code_format");
@@ -112,6 +113,7 @@ lazy_static! {
         m.insert("osc_cmd_tpl", "oscillation_command_template");
         m.insert("osc_stat_t", "oscillation_state_topic");
         m.insert("osc_val_tpl", "oscillation_value_template");
+        m.insert("p", "platform");
         m.insert("pause_cmd_t", "pause_command_topic");
         m.insert("pause_mw_cmd_tpl", "pause_command_template");
         m.insert("pct_cmd_t", "percentage_command_topic");
@@ -0,0 +1,432 @@
+use crate::{
+    constants::PAYLOAD_NONE,
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_variables, try_render_template, MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map};
+use skep_core::{
+    constants::{
+        STATE_ALARM_ARMED_AWAY, STATE_ALARM_ARMED_CUSTOM_BYPASS, STATE_ALARM_ARMED_HOME,
+        STATE_ALARM_ARMED_NIGHT, STATE_ALARM_ARMED_VACATION, STATE_ALARM_ARMING,
+        STATE_ALARM_DISARMED, STATE_ALARM_DISARMING, STATE_ALARM_PENDING, STATE_ALARM_TRIGGERED,
+        STATE_UNKNOWN,
+    },
+    states::State,
+};
+use strum_macros::{Display, EnumString};
+
+pub struct MqttAlarmControlPanelPlugin;
+
+impl Plugin for MqttAlarmControlPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_alarm_control_panel_state)
+        .observe(on_alarm_control_panel_command);
+    }
+}
+
+const DOMAIN: &str = "alarm_control_panel";
+
+const DEFAULT_COMMAND_TEMPLATE: &str = "{{action}}";
+const DEFAULT_PAYLOAD_ARM_AWAY: &str = "ARM_AWAY";
+const DEFAULT_PAYLOAD_ARM_HOME: &str = "ARM_HOME";
+const DEFAULT_PAYLOAD_ARM_NIGHT: &str = "ARM_NIGHT";
+const DEFAULT_PAYLOAD_ARM_VACATION: &str = "ARM_VACATION";
+const DEFAULT_PAYLOAD_ARM_CUSTOM_BYPASS: &str = "ARM_CUSTOM_BYPASS";
+const DEFAULT_PAYLOAD_DISARM: &str = "DISARM";
+const DEFAULT_PAYLOAD_TRIGGER: &str = "TRIGGER";
+/// The code is entered as a number and validated remotely.
+const REMOTE_CODE: &str = "REMOTE_CODE";
+/// The code is entered as text and validated remotely.
+const REMOTE_CODE_TEXT: &str = "REMOTE_CODE_TEXT";
+
+const ALARM_STATES: &[&str] = &[
+    STATE_ALARM_DISARMED,
+    STATE_ALARM_ARMED_HOME,
+    STATE_ALARM_ARMED_AWAY,
+    STATE_ALARM_ARMED_NIGHT,
+    STATE_ALARM_ARMED_VACATION,
+    STATE_ALARM_ARMED_CUSTOM_BYPASS,
+    STATE_ALARM_PENDING,
+    STATE_ALARM_ARMING,
+    STATE_ALARM_DISARMING,
+    STATE_ALARM_TRIGGERED,
+];
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttAlarmControlPanelConfiguration>(
+            payload.payload.clone(),
+        ) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid alarm_control_panel config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        cmds.insert((
+            AlarmControlPanelAttributes::from(&config),
+            config,
+            MQTTPlatformState,
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AlarmControlPanelFeature {
+    ArmHome,
+    ArmAway,
+    ArmNight,
+    ArmVacation,
+    ArmCustomBypass,
+    Trigger,
+}
+
+fn default_supported_features() -> Vec<AlarmControlPanelFeature> {
+    vec![
+        AlarmControlPanelFeature::ArmHome,
+        AlarmControlPanelFeature::ArmAway,
+        AlarmControlPanelFeature::ArmNight,
+        AlarmControlPanelFeature::ArmVacation,
+        AlarmControlPanelFeature::ArmCustomBypass,
+        AlarmControlPanelFeature::Trigger,
+    ]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// How a code is entered in the frontend.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeFormat {
+    Number,
+    Text,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttAlarmControlPanelConfiguration {
+    pub command_topic: String,
+    pub command_template: Option<String>,
+    pub state_topic: String,
+    pub value_template: Option<String>,
+    /// A fixed code to validate commands against, or `REMOTE_CODE`/`REMOTE_CODE_TEXT` to pass the
+    /// entered code on to the device.
+    pub code: Option<String>,
+    #[serde(default = "default_true")]
+    pub code_arm_required: bool,
+    #[serde(default = "default_true")]
+    pub code_disarm_required: bool,
+    #[serde(default = "default_true")]
+    pub code_trigger_required: bool,
+    pub payload_arm_away: Option<String>,
+    pub payload_arm_home: Option<String>,
+    pub payload_arm_night: Option<String>,
+    pub payload_arm_vacation: Option<String>,
+    pub payload_arm_custom_bypass: Option<String>,
+    pub payload_disarm: Option<String>,
+    pub payload_trigger: Option<String>,
+    #[serde(default = "default_supported_features")]
+    pub supported_features: Vec<AlarmControlPanelFeature>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttAlarmControlPanelConfiguration {
+    pub fn code_format(&self) -> Option<CodeFormat> {
+        match self.code.as_deref()? {
+            REMOTE_CODE => Some(CodeFormat::Number),
+            REMOTE_CODE_TEXT => Some(CodeFormat::Text),
+            code if code.chars().all(|c| c.is_ascii_digit()) => Some(CodeFormat::Number),
+            _ => Some(CodeFormat::Text),
+        }
+    }
+
+    /// Check the entered code when the command requires one. A remote code only has to be
+    /// present, it is validated by the device.
+    pub fn validate_code(&self, code: Option<&str>, required: bool) -> bool {
+        match self.code.as_deref() {
+            None => true,
+            Some(_) if !required => true,
+            Some(REMOTE_CODE) | Some(REMOTE_CODE_TEXT) => code.is_some(),
+            Some(expected) => code == Some(expected),
+        }
+    }
+
+    /// Map a rendered state payload to one of the `STATE_ALARM_*` values.
+    pub fn parse_state(&self, payload: &str) -> Option<&'static str> {
+        if payload == PAYLOAD_NONE {
+            return Some(STATE_UNKNOWN);
+        }
+        ALARM_STATES
+            .iter()
+            .find(|state| **state == payload)
+            .copied()
+    }
+
+    /// The command payload for an action, together with whether it requires a code.
+    fn action_payload(&self, command: &AlarmControlPanelCommand) -> (&str, bool) {
+        match command {
+            AlarmControlPanelCommand::Disarm { .. } => (
+                self.payload_disarm
+                    .as_deref()
+                    .unwrap_or(DEFAULT_PAYLOAD_DISARM),
+                self.code_disarm_required,
+            ),
+            AlarmControlPanelCommand::Trigger { .. } => (
+                self.payload_trigger
+                    .as_deref()
+                    .unwrap_or(DEFAULT_PAYLOAD_TRIGGER),
+                self.code_trigger_required,
+            ),
+            AlarmControlPanelCommand::Arm { mode, .. } => (
+                match mode {
+                    AlarmArmMode::Home => self
+                        .payload_arm_home
+                        .as_deref()
+                        .unwrap_or(DEFAULT_PAYLOAD_ARM_HOME),
+                    AlarmArmMode::Away => self
+                        .payload_arm_away
+                        .as_deref()
+                        .unwrap_or(DEFAULT_PAYLOAD_ARM_AWAY),
+                    AlarmArmMode::Night => self
+                        .payload_arm_night
+                        .as_deref()
+                        .unwrap_or(DEFAULT_PAYLOAD_ARM_NIGHT),
+                    AlarmArmMode::Vacation => self
+                        .payload_arm_vacation
+                        .as_deref()
+                        .unwrap_or(DEFAULT_PAYLOAD_ARM_VACATION),
+                    AlarmArmMode::CustomBypass => self
+                        .payload_arm_custom_bypass
+                        .as_deref()
+                        .unwrap_or(DEFAULT_PAYLOAD_ARM_CUSTOM_BYPASS),
+                },
+                self.code_arm_required,
+            ),
+        }
+    }
+}
+
+/// Code requirements and supported arm modes of an alarm control panel.
+#[derive(Debug, Component, Clone, Serialize)]
+pub struct AlarmControlPanelAttributes {
+    pub code_format: Option<CodeFormat>,
+    pub code_arm_required: bool,
+    pub supported_features: Vec<AlarmControlPanelFeature>,
+}
+
+impl From<&MqttAlarmControlPanelConfiguration> for AlarmControlPanelAttributes {
+    fn from(config: &MqttAlarmControlPanelConfiguration) -> Self {
+        Self {
+            code_format: config.code_format(),
+            code_arm_required: config.code_arm_required,
+            supported_features: config.supported_features.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlarmArmMode {
+    Home,
+    Away,
+    Night,
+    Vacation,
+    CustomBypass,
+}
+
+impl AlarmArmMode {
+    fn feature(&self) -> AlarmControlPanelFeature {
+        match self {
+            AlarmArmMode::Home => AlarmControlPanelFeature::ArmHome,
+            AlarmArmMode::Away => AlarmControlPanelFeature::ArmAway,
+            AlarmArmMode::Night => AlarmControlPanelFeature::ArmNight,
+            AlarmArmMode::Vacation => AlarmControlPanelFeature::ArmVacation,
+            AlarmArmMode::CustomBypass => AlarmControlPanelFeature::ArmCustomBypass,
+        }
+    }
+}
+
+/// Commands accepted by an MQTT alarm control panel, triggered with the panel as target.
+#[derive(Debug, Event, Clone, PartialEq, Eq)]
+pub enum AlarmControlPanelCommand {
+    Disarm {
+        code: Option<String>,
+    },
+    Arm {
+        mode: AlarmArmMode,
+        code: Option<String>,
+    },
+    Trigger {
+        code: Option<String>,
+    },
+}
+
+impl AlarmControlPanelCommand {
+    fn code(&self) -> Option<&str> {
+        match self {
+            AlarmControlPanelCommand::Disarm { code }
+            | AlarmControlPanelCommand::Arm { code, .. }
+            | AlarmControlPanelCommand::Trigger { code } => code.as_deref(),
+        }
+    }
+}
+
+fn handle_alarm_control_panel_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_panel: Query<(&MqttAlarmControlPanelConfiguration, &mut State)>,
+) {
+    let Ok((config, mut state)) = q_panel.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic != topic {
+        return;
+    }
+    let value = try_render_template(&config.value_template, &topic_message.event().payload)
+        .unwrap_or_default();
+    match config.parse_state(&value) {
+        Some(new_state) => state.update(new_state),
+        None => warn!(
+            "Ignoring alarm_control_panel state {:?} on {}",
+            value, topic
+        ),
+    }
+}
+
+/// Alarm control panels have no optimistic mode, the state is only updated by the device.
+fn on_alarm_control_panel_command(
+    trigger: Trigger<AlarmControlPanelCommand>,
+    publisher: MqttPublisher,
+    q_panel: Query<&MqttAlarmControlPanelConfiguration>,
+) {
+    let entity = trigger.entity();
+    let Ok(config) = q_panel.get(entity) else {
+        return;
+    };
+    let command = trigger.event();
+
+    let feature = match command {
+        AlarmControlPanelCommand::Arm { mode, .. } => Some(mode.feature()),
+        AlarmControlPanelCommand::Trigger { .. } => Some(AlarmControlPanelFeature::Trigger),
+        AlarmControlPanelCommand::Disarm { .. } => None,
+    };
+    if let Some(feature) = feature {
+        if !config.supported_features.contains(&feature) {
+            warn!(
+                "Alarm control panel {:?} does not support {}",
+                entity, feature
+            );
+            return;
+        }
+    }
+
+    let (action, code_required) = config.action_payload(command);
+    if !config.validate_code(command.code(), code_required) {
+        warn!("Invalid code for alarm control panel {:?}", entity);
+        return;
+    }
+
+    let mut variables = Map::new();
+    variables.insert("action".to_string(), json!(action));
+    variables.insert("code".to_string(), json!(command.code()));
+    let template = config
+        .command_template
+        .as_deref()
+        .unwrap_or(DEFAULT_COMMAND_TEMPLATE);
+    let payload = match try_render_command_variables(template, &variables) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!("Failed to render alarm_control_panel command: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = publisher.publish(
+        entity,
+        &config.command_topic,
+        config.qos,
+        config.retain,
+        payload,
+    ) {
+        warn!("Failed to publish alarm_control_panel command: {}", e);
+    }
+}
+
+#[test]
+fn test_alarm_control_panel_configuration() {
+    let json = r#"
+    {
+        "command_topic": "alarm/set",
+        "state_topic": "alarm/state",
+        "code": "1234",
+        "code_arm_required": false,
+        "supported_features": ["arm_home", "arm_away"]
+    }
+    "#;
+    let config: MqttAlarmControlPanelConfiguration = serde_json::from_str(json).unwrap();
+
+    assert_eq!(config.code_format(), Some(CodeFormat::Number));
+    assert!(config.validate_code(None, config.code_arm_required));
+    assert!(!config.validate_code(Some("0000"), config.code_disarm_required));
+    assert!(config.validate_code(Some("1234"), config.code_disarm_required));
+    assert_eq!(
+        config.parse_state("armed_night"),
+        Some(STATE_ALARM_ARMED_NIGHT)
+    );
+    assert_eq!(config.parse_state("None"), Some(STATE_UNKNOWN));
+    assert_eq!(config.parse_state("armed"), None);
+    assert_eq!(
+        config.action_payload(&AlarmControlPanelCommand::Arm {
+            mode: AlarmArmMode::Away,
+            code: None
+        }),
+        ("ARM_AWAY", false)
+    );
+
+    let config: MqttAlarmControlPanelConfiguration = serde_json::from_str(
+        r#"{"command_topic": "alarm/set", "state_topic": "alarm/state", "code": "REMOTE_CODE_TEXT"}"#,
+    )
+    .unwrap();
+    assert_eq!(config.code_format(), Some(CodeFormat::Text));
+    assert!(config.validate_code(Some("secret"), true));
+    assert!(!config.validate_code(None, true));
+    assert_eq!(config.supported_features.len(), 6);
+}
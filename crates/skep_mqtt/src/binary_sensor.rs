@@ -1,15 +1,21 @@
 use crate::{
+    constants::{DEFAULT_PAYLOAD_OFF, DEFAULT_PAYLOAD_ON, PAYLOAD_NONE},
     discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{delayed_state, try_render_template, MQTTPlatformState},
     SkepMqttPlatform,
 };
 use bevy_app::{App, Plugin, Update};
-use bevy_ecs::{
-    observer::Trigger,
-    prelude::{Added, Commands, Query},
-};
-use bevy_log::debug;
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
 use serde::{Deserialize, Serialize};
-use skep_core::typing::SetupConfigEntry;
+use skep_core::{
+    constants::{STATE_OFF, STATE_ON, STATE_UNAVAILABLE, STATE_UNKNOWN},
+    helper::event::DelayedActions,
+    states::State,
+    typing::SetupConfigEntry,
+};
+use strum_macros::{Display, EnumString};
 
 pub struct MqttBinarySensorPlugin;
 
@@ -19,7 +25,8 @@ impl Plugin for MqttBinarySensorPlugin {
             Update,
             (create_or_update_discovery_payload, on_mqtt_platform_added),
         )
-        .observe(on_setup_entry);
+        .observe(on_setup_entry)
+        .observe(handle_binary_sensor_state);
     }
 }
 
@@ -38,14 +45,228 @@ fn on_setup_entry(trigger: Trigger<SetupConfigEntry>) {
 }
 
 fn create_or_update_discovery_payload(
-    q_discovery: Query<(&MQTTDiscoveryHash, &MQTTDiscoveryPayload), Added<MQTTDiscoveryHash>>,
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
 ) {
-    for (hash, payload) in q_discovery.iter() {
-        if hash.component == DOMAIN {
-            debug!("create_or_update_discovery_payload {:#?}", payload);
+    for (entity, hash, payload, opt_state) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttBinarySensorConfiguration>(
+            payload.payload.clone(),
+        ) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid binary_sensor config {}: {}", hash, e);
+                continue;
+            }
+        };
+        if let Err(e) = config.validate() {
+            warn!("invalid binary_sensor config {}: {}", hash, e);
+            continue;
+        }
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        if opt_state.is_none() {
+            // An expiring sensor is unavailable until its first state arrives
+            let state = if config.expire_after.is_some() {
+                STATE_UNAVAILABLE
+            } else {
+                STATE_UNKNOWN
+            };
+            cmds.insert(State::new(state.to_string()));
         }
+        cmds.insert((config, MQTTPlatformState));
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MqttBinarySensorConfiguration {}
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum BinarySensorDeviceClass {
+    Battery,
+    BatteryCharging,
+    CarbonMonoxide,
+    Cold,
+    Connectivity,
+    Door,
+    GarageDoor,
+    Gas,
+    Heat,
+    Light,
+    Lock,
+    Moisture,
+    Motion,
+    Moving,
+    Occupancy,
+    Opening,
+    Plug,
+    Power,
+    Presence,
+    Problem,
+    Running,
+    Safety,
+    Smoke,
+    Sound,
+    Tamper,
+    Update,
+    Vibration,
+    Window,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttBinarySensorConfiguration {
+    pub state_topic: String,
+    pub value_template: Option<String>,
+    pub payload_on: Option<String>,
+    pub payload_off: Option<String>,
+    pub device_class: Option<BinarySensorDeviceClass>,
+    /// Seconds after which the sensor turns off again after turning on.
+    pub off_delay: Option<f32>,
+    /// Seconds after which the state becomes unavailable when no new state is received.
+    pub expire_after: Option<f32>,
+    /// Update the state even if it did not change
+    pub force_update: Option<bool>,
+    pub qos: Option<u8>,
+}
+
+impl MqttBinarySensorConfiguration {
+    pub fn payload_on(&self) -> &str {
+        self.payload_on.as_deref().unwrap_or(DEFAULT_PAYLOAD_ON)
+    }
+
+    pub fn payload_off(&self) -> &str {
+        self.payload_off.as_deref().unwrap_or(DEFAULT_PAYLOAD_OFF)
+    }
+
+    pub fn force_update(&self) -> bool {
+        self.force_update.unwrap_or(false)
+    }
+
+    /// Check that `off_delay` and `expire_after` are positive, finite durations.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (key, secs) in [
+            ("off_delay", self.off_delay),
+            ("expire_after", self.expire_after),
+        ] {
+            if let Some(secs) = secs {
+                if !secs.is_finite() || secs <= 0.0 {
+                    return Err(anyhow::anyhow!(
+                        "{} must be a positive number of seconds",
+                        key
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Map a rendered state payload to `STATE_ON`/`STATE_OFF`/`STATE_UNKNOWN`.
+    pub fn parse_state(&self, payload: &str) -> Option<&'static str> {
+        if payload == self.payload_on() {
+            Some(STATE_ON)
+        } else if payload == self.payload_off() {
+            Some(STATE_OFF)
+        } else if payload == PAYLOAD_NONE {
+            Some(STATE_UNKNOWN)
+        } else {
+            None
+        }
+    }
+}
+
+fn handle_binary_sensor_state(
+    topic_message: Trigger<TopicMessage>,
+    mut commands: Commands,
+    mut q_binary_sensor: Query<(&MqttBinarySensorConfiguration, &mut State)>,
+) {
+    let entity = topic_message.entity();
+    let Ok((config, mut state)) = q_binary_sensor.get_mut(entity) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic != topic {
+        return;
+    }
+
+    let payload = try_render_template(&config.value_template, &topic_message.event().payload)
+        .unwrap_or_default();
+    let Some(new_state) = config.parse_state(&payload) else {
+        warn!(
+            "Ignoring binary_sensor state payload {:?} on {}",
+            payload, topic
+        );
+        return;
+    };
+    if config.force_update() || state.state != new_state {
+        state.update(new_state);
+    }
+
+    // Every state restarts the expiration and cancels a pending off delay
+    let mut actions = vec![];
+    if let Some(expire_after) = config.expire_after {
+        actions.extend(delayed_state(entity, expire_after, STATE_UNAVAILABLE));
+    }
+    if let (STATE_ON, Some(off_delay)) = (new_state, config.off_delay) {
+        actions.extend(delayed_state(entity, off_delay, STATE_OFF));
+    }
+    commands.entity(entity).insert(DelayedActions(actions));
+}
+
+#[test]
+fn test_binary_sensor_configuration() {
+    let json = r#"
+    {
+        "state_topic": "zigbee2mqtt/hallway_motion",
+        "value_template": "{{ value_json.occupancy }}",
+        "payload_on": "true",
+        "payload_off": "false",
+        "device_class": "motion",
+        "off_delay": 30,
+        "expire_after": 3600
+    }
+    "#;
+    let config: MqttBinarySensorConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.device_class, Some(BinarySensorDeviceClass::Motion));
+    assert_eq!(config.off_delay, Some(30.0));
+    assert!(!config.force_update());
+    assert!(config.validate().is_ok());
+
+    let payload = try_render_template(&config.value_template, br#"{"occupancy": true}"#).unwrap();
+    assert_eq!(config.parse_state(&payload), Some(STATE_ON));
+    assert_eq!(config.parse_state("false"), Some(STATE_OFF));
+    assert_eq!(config.parse_state("None"), Some(STATE_UNKNOWN));
+    assert_eq!(config.parse_state("ON"), None);
+
+    let config: MqttBinarySensorConfiguration =
+        serde_json::from_str(r#"{"state_topic": "door/contact", "device_class": "garage_door"}"#)
+            .unwrap();
+    assert_eq!(config.parse_state("ON"), Some(STATE_ON));
+    assert_eq!(config.parse_state("OFF"), Some(STATE_OFF));
+    assert_eq!(config.parse_state("1"), None);
+
+    for off_delay in ["-5", "0"] {
+        let config: MqttBinarySensorConfiguration = serde_json::from_str(&format!(
+            r#"{{"state_topic": "door/contact", "off_delay": {}}}"#,
+            off_delay
+        ))
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+    let config = MqttBinarySensorConfiguration {
+        expire_after: Some(f32::NAN),
+        ..serde_json::from_str(r#"{"state_topic": "door/contact"}"#).unwrap()
+    };
+    assert!(config.validate().is_err());
+    assert!(delayed_state(Entity::PLACEHOLDER, f32::INFINITY, STATE_OFF).is_none());
+    assert!(delayed_state(Entity::PLACEHOLDER, -1.0, STATE_OFF).is_none());
+}
@@ -0,0 +1,161 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_template, MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use skep_core::{constants::STATE_UNKNOWN, states::State};
+use strum_macros::{Display, EnumString};
+
+pub struct MqttButtonPlugin;
+
+impl Plugin for MqttButtonPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(on_button_command);
+    }
+}
+
+const DOMAIN: &str = "button";
+
+const DEFAULT_PAYLOAD_PRESS: &str = "PRESS";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttButtonConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid button config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        cmds.insert((config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ButtonDeviceClass {
+    Identify,
+    Restart,
+    Update,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttButtonConfiguration {
+    pub command_topic: String,
+    pub command_template: Option<String>,
+    pub payload_press: Option<String>,
+    pub device_class: Option<ButtonDeviceClass>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttButtonConfiguration {
+    pub fn payload_press(&self) -> &str {
+        self.payload_press
+            .as_deref()
+            .unwrap_or(DEFAULT_PAYLOAD_PRESS)
+    }
+}
+
+/// Commands accepted by an MQTT button entity, triggered with the button as target.
+#[derive(Debug, Event, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonCommand {
+    Press,
+}
+
+/// Publish the press payload, the state of a button is the time it was last pressed.
+fn on_button_command(
+    trigger: Trigger<ButtonCommand>,
+    publisher: MqttPublisher,
+    mut q_button: Query<(&MqttButtonConfiguration, &mut State)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state)) = q_button.get_mut(entity) else {
+        return;
+    };
+    let ButtonCommand::Press = trigger.event();
+
+    let payload =
+        match try_render_command_template(&config.command_template, &json!(config.payload_press()))
+        {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to render button command: {}", e);
+                return;
+            }
+        };
+    if let Err(e) = publisher.publish(
+        entity,
+        &config.command_topic,
+        config.qos,
+        config.retain,
+        payload,
+    ) {
+        warn!("Failed to publish button command: {}", e);
+        return;
+    }
+    state.update(Utc::now().to_rfc3339());
+}
+
+#[test]
+fn test_button_configuration() {
+    let json = r#"
+    {
+        "command_topic": "zigbee2mqtt/bulb/set",
+        "command_template": "{\"effect\": \"{{ value }}\"}",
+        "payload_press": "blink",
+        "device_class": "identify"
+    }
+    "#;
+    let config: MqttButtonConfiguration = serde_json::from_str(json).unwrap();
+
+    assert_eq!(config.device_class, Some(ButtonDeviceClass::Identify));
+    assert_eq!(
+        try_render_command_template(&config.command_template, &json!(config.payload_press()))
+            .unwrap(),
+        r#"{"effect": "blink"}"#
+    );
+
+    let config: MqttButtonConfiguration =
+        serde_json::from_str(r#"{"command_topic": "device/restart"}"#).unwrap();
+    assert_eq!(config.payload_press(), "PRESS");
+    assert!(serde_json::from_str::<MqttButtonConfiguration>("{}").is_err());
+}
@@ -0,0 +1,144 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{MQTTAttributesBlocked, MQTTPlatformState},
+    image::{decode_image, ImageEncoding, ImageFrame, DEFAULT_CONTENT_TYPE},
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::Children;
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use skep_core::{constants::STATE_IDLE, states::State};
+use std::collections::HashSet;
+
+lazy_static! {
+    static ref MQTT_CAMERA_ATTRIBUTES_BLOCKED: HashSet<&'static str> =
+        HashSet::from(["access_token", "brand", "model_name", "motion_detection"]);
+}
+
+pub struct MqttCameraPlugin;
+
+impl Plugin for MqttCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_camera_frame);
+    }
+}
+
+const DOMAIN: &str = "camera";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_state, opt_children) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttCameraConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid camera config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            [config.topic.as_str()],
+            config.qos.unwrap_or(0),
+        );
+        let mut cmds = commands.entity(entity);
+        cmds.insert((
+            config,
+            MQTTPlatformState,
+            MQTTAttributesBlocked(&MQTT_CAMERA_ATTRIBUTES_BLOCKED),
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_IDLE.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttCameraConfiguration {
+    /// Receives the camera frames as JPEG images
+    pub topic: String,
+    pub image_encoding: Option<ImageEncoding>,
+    pub qos: Option<u8>,
+}
+
+/// Store each received frame as the [`ImageFrame`] of the camera.
+fn handle_camera_frame(
+    topic_message: Trigger<TopicMessage>,
+    mut commands: Commands,
+    q_camera: Query<&MqttCameraConfiguration>,
+) {
+    let entity = topic_message.entity();
+    let Ok(config) = q_camera.get(entity) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.topic != topic {
+        return;
+    }
+    match decode_image(&topic_message.event().payload, config.image_encoding) {
+        Ok(content) => {
+            commands
+                .entity(entity)
+                .insert(ImageFrame::new(content, DEFAULT_CONTENT_TYPE));
+        }
+        Err(e) => warn!("Ignoring camera frame on {}: {}", topic, e),
+    }
+}
+
+#[test]
+fn test_camera_configuration() {
+    let json = r#"
+    {
+        "topic": "doorbell/camera",
+        "image_encoding": "b64"
+    }
+    "#;
+    let config: MqttCameraConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.image_encoding, Some(ImageEncoding::Base64));
+
+    let config: MqttCameraConfiguration =
+        serde_json::from_str(r#"{"topic": "doorbell/camera"}"#).unwrap();
+    assert_eq!(config.image_encoding, None);
+
+    let frame = ImageFrame::new(
+        bytes::Bytes::from_static(b"\xff\xd8\xff"),
+        DEFAULT_CONTENT_TYPE,
+    );
+    assert_eq!(frame.content_type, "image/jpeg");
+    assert!(frame.timestamp <= chrono::Utc::now());
+}
@@ -0,0 +1,674 @@
+use crate::{
+    constants::{DEFAULT_PAYLOAD_OFF, DEFAULT_PAYLOAD_ON, PAYLOAD_NONE},
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{
+        try_render_command_template, try_render_template, MQTTAttributesBlocked, MQTTPlatformState,
+        MqttPublisher,
+    },
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::Children;
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use skep_core::{constants::STATE_UNKNOWN, states::State};
+use std::{collections::HashSet, str::FromStr};
+use strum_macros::{Display, EnumString};
+
+lazy_static! {
+    static ref MQTT_CLIMATE_ATTRIBUTES_BLOCKED: HashSet<&'static str> = HashSet::from([
+        "current_humidity",
+        "current_temperature",
+        "fan_mode",
+        "fan_modes",
+        "humidity",
+        "hvac_action",
+        "hvac_modes",
+        "max_humidity",
+        "max_temp",
+        "min_humidity",
+        "min_temp",
+        "preset_mode",
+        "preset_modes",
+        "swing_mode",
+        "swing_modes",
+        "target_temp_high",
+        "target_temp_low",
+        "target_temp_step",
+        "temperature",
+    ]);
+}
+
+pub struct MqttClimatePlugin;
+
+impl Plugin for MqttClimatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_climate_state)
+        .observe(on_climate_command);
+    }
+}
+
+const DOMAIN: &str = "climate";
+
+const DEFAULT_MIN_TEMP: f32 = 7.0;
+const DEFAULT_MAX_TEMP: f32 = 35.0;
+const DEFAULT_MIN_HUMIDITY: f32 = 30.0;
+const DEFAULT_MAX_HUMIDITY: f32 = 99.0;
+const PRESET_NONE: &str = "none";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    mut q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&mut ClimateAttributes>,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_state, opt_attributes, opt_children) in q_discovery.iter_mut() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttClimateConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid climate config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            config.state_topics(),
+            config.qos.unwrap_or(0),
+        );
+        let mut cmds = commands.entity(entity);
+        match opt_attributes {
+            Some(mut attributes) => attributes.update_from_config(&config),
+            None => {
+                let mut attributes = ClimateAttributes::default();
+                attributes.update_from_config(&config);
+                attributes.target_temperature = config.initial;
+                cmds.insert(attributes);
+            }
+        }
+        cmds.insert((
+            config,
+            MQTTPlatformState,
+            MQTTAttributesBlocked(&MQTT_CLIMATE_ATTRIBUTES_BLOCKED),
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[strum(ascii_case_insensitive)]
+pub enum HvacMode {
+    Off,
+    Heat,
+    Cool,
+    HeatCool,
+    Auto,
+    Dry,
+    FanOnly,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[strum(ascii_case_insensitive)]
+pub enum HvacAction {
+    Off,
+    Preheating,
+    Heating,
+    Cooling,
+    Drying,
+    Fan,
+    Idle,
+    Defrosting,
+}
+
+fn default_modes() -> Vec<HvacMode> {
+    vec![
+        HvacMode::Auto,
+        HvacMode::Off,
+        HvacMode::Cool,
+        HvacMode::Heat,
+        HvacMode::Dry,
+        HvacMode::FanOnly,
+    ]
+}
+
+fn default_fan_modes() -> Vec<String> {
+    ["auto", "low", "medium", "high"].map(String::from).to_vec()
+}
+
+fn default_swing_modes() -> Vec<String> {
+    ["on", "off"].map(String::from).to_vec()
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttClimateConfiguration {
+    pub action_topic: Option<String>,
+    pub action_template: Option<String>,
+    pub current_humidity_topic: Option<String>,
+    pub current_humidity_template: Option<String>,
+    pub current_temperature_topic: Option<String>,
+    pub current_temperature_template: Option<String>,
+    pub fan_mode_command_topic: Option<String>,
+    pub fan_mode_command_template: Option<String>,
+    pub fan_mode_state_topic: Option<String>,
+    pub fan_mode_state_template: Option<String>,
+    #[serde(default = "default_fan_modes")]
+    pub fan_modes: Vec<String>,
+    pub mode_command_topic: Option<String>,
+    pub mode_command_template: Option<String>,
+    pub mode_state_topic: Option<String>,
+    pub mode_state_template: Option<String>,
+    #[serde(default = "default_modes")]
+    pub modes: Vec<HvacMode>,
+    pub power_command_topic: Option<String>,
+    pub power_command_template: Option<String>,
+    pub payload_on: Option<String>,
+    pub payload_off: Option<String>,
+    pub preset_mode_command_topic: Option<String>,
+    pub preset_mode_command_template: Option<String>,
+    pub preset_mode_state_topic: Option<String>,
+    pub preset_mode_value_template: Option<String>,
+    #[serde(default)]
+    pub preset_modes: Vec<String>,
+    pub swing_mode_command_topic: Option<String>,
+    pub swing_mode_command_template: Option<String>,
+    pub swing_mode_state_topic: Option<String>,
+    pub swing_mode_state_template: Option<String>,
+    #[serde(default = "default_swing_modes")]
+    pub swing_modes: Vec<String>,
+    pub target_humidity_command_topic: Option<String>,
+    pub target_humidity_command_template: Option<String>,
+    pub target_humidity_state_topic: Option<String>,
+    pub target_humidity_state_template: Option<String>,
+    pub temperature_command_topic: Option<String>,
+    pub temperature_command_template: Option<String>,
+    pub temperature_state_topic: Option<String>,
+    pub temperature_state_template: Option<String>,
+    pub temperature_high_command_topic: Option<String>,
+    pub temperature_high_command_template: Option<String>,
+    pub temperature_high_state_topic: Option<String>,
+    pub temperature_high_state_template: Option<String>,
+    pub temperature_low_command_topic: Option<String>,
+    pub temperature_low_command_template: Option<String>,
+    pub temperature_low_state_topic: Option<String>,
+    pub temperature_low_state_template: Option<String>,
+    /// Initial target temperature
+    pub initial: Option<f32>,
+    pub min_temp: Option<f32>,
+    pub max_temp: Option<f32>,
+    pub min_humidity: Option<f32>,
+    pub max_humidity: Option<f32>,
+    pub precision: Option<f32>,
+    pub temp_step: Option<f32>,
+    pub temperature_unit: Option<String>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttClimateConfiguration {
+    pub fn payload_on(&self) -> &str {
+        self.payload_on.as_deref().unwrap_or(DEFAULT_PAYLOAD_ON)
+    }
+
+    pub fn payload_off(&self) -> &str {
+        self.payload_off.as_deref().unwrap_or(DEFAULT_PAYLOAD_OFF)
+    }
+
+    /// Whether a value with the given state topic is updated optimistically after a command.
+    fn optimistic_for(&self, state_topic: &Option<String>) -> bool {
+        self.optimistic.unwrap_or(false) || state_topic.is_none()
+    }
+
+    pub fn state_topics(&self) -> Vec<&str> {
+        [
+            &self.action_topic,
+            &self.current_humidity_topic,
+            &self.current_temperature_topic,
+            &self.fan_mode_state_topic,
+            &self.mode_state_topic,
+            &self.preset_mode_state_topic,
+            &self.swing_mode_state_topic,
+            &self.target_humidity_state_topic,
+            &self.temperature_state_topic,
+            &self.temperature_high_state_topic,
+            &self.temperature_low_state_topic,
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect()
+    }
+}
+
+/// Current climate values next to the HVAC mode stored in [`State`].
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct ClimateAttributes {
+    pub current_temperature: Option<f32>,
+    pub current_humidity: Option<f32>,
+    pub target_temperature: Option<f32>,
+    pub target_temperature_high: Option<f32>,
+    pub target_temperature_low: Option<f32>,
+    pub target_humidity: Option<f32>,
+    pub fan_mode: Option<String>,
+    pub swing_mode: Option<String>,
+    pub preset_mode: Option<String>,
+    pub hvac_action: Option<HvacAction>,
+    pub hvac_modes: Vec<HvacMode>,
+    pub fan_modes: Vec<String>,
+    pub swing_modes: Vec<String>,
+    pub preset_modes: Vec<String>,
+    pub min_temp: f32,
+    pub max_temp: f32,
+    pub min_humidity: f32,
+    pub max_humidity: f32,
+    pub temperature_unit: Option<String>,
+}
+
+impl ClimateAttributes {
+    fn update_from_config(&mut self, config: &MqttClimateConfiguration) {
+        self.hvac_modes = config.modes.clone();
+        self.fan_modes = config.fan_modes.clone();
+        self.swing_modes = config.swing_modes.clone();
+        self.preset_modes = config.preset_modes.clone();
+        self.min_temp = config.min_temp.unwrap_or(DEFAULT_MIN_TEMP);
+        self.max_temp = config.max_temp.unwrap_or(DEFAULT_MAX_TEMP);
+        self.min_humidity = config.min_humidity.unwrap_or(DEFAULT_MIN_HUMIDITY);
+        self.max_humidity = config.max_humidity.unwrap_or(DEFAULT_MAX_HUMIDITY);
+        self.temperature_unit = config.temperature_unit.clone();
+    }
+}
+
+/// Commands accepted by an MQTT climate entity, triggered with the climate as target.
+#[derive(Debug, Event, Clone, PartialEq)]
+pub enum ClimateCommand {
+    SetHvacMode(HvacMode),
+    /// Set a single target temperature and/or a high/low range
+    SetTemperature {
+        temperature: Option<f32>,
+        target_temp_high: Option<f32>,
+        target_temp_low: Option<f32>,
+        hvac_mode: Option<HvacMode>,
+    },
+    SetHumidity(f32),
+    SetFanMode(String),
+    SetSwingMode(String),
+    SetPresetMode(String),
+    TurnOn,
+    TurnOff,
+}
+
+pub(crate) fn render_float(template: &Option<String>, payload: &[u8]) -> Option<f32> {
+    let value = try_render_template(template, payload).ok()?;
+    if value == PAYLOAD_NONE {
+        return None;
+    }
+    value.trim().parse::<f32>().ok()
+}
+
+/// Render a mode payload and check that it is one of `options`, `None` resets the mode.
+pub(crate) fn render_option(
+    template: &Option<String>,
+    payload: &[u8],
+    options: &[String],
+) -> Option<Option<String>> {
+    let value = try_render_template(template, payload).ok()?;
+    if value == PAYLOAD_NONE {
+        Some(None)
+    } else if options.iter().any(|o| *o == value) {
+        Some(Some(value))
+    } else {
+        warn!("Ignoring invalid mode {:?}", value);
+        None
+    }
+}
+
+fn handle_climate_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_climate: Query<(
+        &MqttClimateConfiguration,
+        &mut State,
+        &mut ClimateAttributes,
+    )>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_climate.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    let payload = &topic_message.event().payload[..];
+    let is_topic = |t: &Option<String>| t.as_deref() == Some(topic);
+
+    if is_topic(&config.current_temperature_topic) {
+        attributes.current_temperature =
+            render_float(&config.current_temperature_template, payload);
+    }
+    if is_topic(&config.current_humidity_topic) {
+        attributes.current_humidity = render_float(&config.current_humidity_template, payload);
+    }
+    if is_topic(&config.temperature_state_topic) {
+        if let Some(value) = render_float(&config.temperature_state_template, payload) {
+            attributes.target_temperature = Some(value);
+        }
+    }
+    if is_topic(&config.temperature_high_state_topic) {
+        if let Some(value) = render_float(&config.temperature_high_state_template, payload) {
+            attributes.target_temperature_high = Some(value);
+        }
+    }
+    if is_topic(&config.temperature_low_state_topic) {
+        if let Some(value) = render_float(&config.temperature_low_state_template, payload) {
+            attributes.target_temperature_low = Some(value);
+        }
+    }
+    if is_topic(&config.target_humidity_state_topic) {
+        if let Some(value) = render_float(&config.target_humidity_state_template, payload) {
+            attributes.target_humidity = Some(value);
+        }
+    }
+    if is_topic(&config.fan_mode_state_topic) {
+        if let Some(mode) =
+            render_option(&config.fan_mode_state_template, payload, &config.fan_modes)
+        {
+            attributes.fan_mode = mode;
+        }
+    }
+    if is_topic(&config.swing_mode_state_topic) {
+        if let Some(mode) = render_option(
+            &config.swing_mode_state_template,
+            payload,
+            &config.swing_modes,
+        ) {
+            attributes.swing_mode = mode;
+        }
+    }
+    if is_topic(&config.preset_mode_state_topic) {
+        if let Ok(value) = try_render_template(&config.preset_mode_value_template, payload) {
+            if value == PRESET_NONE || value == PAYLOAD_NONE {
+                attributes.preset_mode = None;
+            } else if config.preset_modes.contains(&value) {
+                attributes.preset_mode = Some(value);
+            } else {
+                warn!("Ignoring invalid climate preset {:?} on {}", value, topic);
+            }
+        }
+    }
+    if is_topic(&config.action_topic) {
+        if let Ok(value) = try_render_template(&config.action_template, payload) {
+            match HvacAction::from_str(&value) {
+                Ok(action) => attributes.hvac_action = Some(action),
+                Err(_) if value == PAYLOAD_NONE => attributes.hvac_action = None,
+                Err(_) => warn!("Ignoring invalid hvac action {:?} on {}", value, topic),
+            }
+        }
+    }
+    if is_topic(&config.mode_state_topic) {
+        if let Ok(value) = try_render_template(&config.mode_state_template, payload) {
+            match HvacMode::from_str(&value) {
+                Ok(mode) if config.modes.contains(&mode) => state.update(mode),
+                _ if value == PAYLOAD_NONE => state.update(STATE_UNKNOWN),
+                _ => warn!("Ignoring invalid hvac mode {:?} on {}", value, topic),
+            }
+        }
+    }
+}
+
+fn on_climate_command(
+    trigger: Trigger<ClimateCommand>,
+    publisher: MqttPublisher,
+    mut q_climate: Query<(
+        &MqttClimateConfiguration,
+        &mut State,
+        &mut ClimateAttributes,
+    )>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state, mut attributes)) = q_climate.get_mut(entity) else {
+        return;
+    };
+
+    let publish = |topic: &Option<String>, template: &Option<String>, value: Value| -> bool {
+        let Some(topic) = topic else {
+            return false;
+        };
+        let payload = match try_render_command_template(template, &value) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to render climate command for {}: {}", topic, e);
+                return false;
+            }
+        };
+        match publisher.publish(entity, topic, config.qos, config.retain, payload) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Failed to publish climate command: {}", e);
+                false
+            }
+        }
+    };
+
+    match trigger.event() {
+        ClimateCommand::SetHvacMode(mode) => {
+            if !config.modes.contains(mode) {
+                warn!("Unsupported hvac mode: {}", mode);
+                return;
+            }
+            if publish(
+                &config.mode_command_topic,
+                &config.mode_command_template,
+                json!(mode),
+            ) && config.optimistic_for(&config.mode_state_topic)
+            {
+                state.update(mode);
+            }
+        }
+        ClimateCommand::SetTemperature {
+            temperature,
+            target_temp_high,
+            target_temp_low,
+            hvac_mode,
+        } => {
+            if let Some(mode) = hvac_mode {
+                if config.modes.contains(mode)
+                    && publish(
+                        &config.mode_command_topic,
+                        &config.mode_command_template,
+                        json!(mode),
+                    )
+                    && config.optimistic_for(&config.mode_state_topic)
+                {
+                    state.update(mode);
+                }
+            }
+            let (min_temp, max_temp) = (attributes.min_temp, attributes.max_temp);
+            let clamp = |value: f32| value.clamp(min_temp, max_temp);
+            if let Some(value) = temperature.map(clamp) {
+                if publish(
+                    &config.temperature_command_topic,
+                    &config.temperature_command_template,
+                    json!(value),
+                ) && config.optimistic_for(&config.temperature_state_topic)
+                {
+                    attributes.target_temperature = Some(value);
+                }
+            }
+            if let Some(value) = target_temp_high.map(clamp) {
+                if publish(
+                    &config.temperature_high_command_topic,
+                    &config.temperature_high_command_template,
+                    json!(value),
+                ) && config.optimistic_for(&config.temperature_high_state_topic)
+                {
+                    attributes.target_temperature_high = Some(value);
+                }
+            }
+            if let Some(value) = target_temp_low.map(clamp) {
+                if publish(
+                    &config.temperature_low_command_topic,
+                    &config.temperature_low_command_template,
+                    json!(value),
+                ) && config.optimistic_for(&config.temperature_low_state_topic)
+                {
+                    attributes.target_temperature_low = Some(value);
+                }
+            }
+        }
+        ClimateCommand::SetHumidity(humidity) => {
+            let value = humidity.clamp(attributes.min_humidity, attributes.max_humidity);
+            if publish(
+                &config.target_humidity_command_topic,
+                &config.target_humidity_command_template,
+                json!(value),
+            ) && config.optimistic_for(&config.target_humidity_state_topic)
+            {
+                attributes.target_humidity = Some(value);
+            }
+        }
+        ClimateCommand::SetFanMode(mode) => {
+            if !config.fan_modes.contains(mode) {
+                warn!("Unsupported fan mode: {}", mode);
+                return;
+            }
+            if publish(
+                &config.fan_mode_command_topic,
+                &config.fan_mode_command_template,
+                json!(mode),
+            ) && config.optimistic_for(&config.fan_mode_state_topic)
+            {
+                attributes.fan_mode = Some(mode.clone());
+            }
+        }
+        ClimateCommand::SetSwingMode(mode) => {
+            if !config.swing_modes.contains(mode) {
+                warn!("Unsupported swing mode: {}", mode);
+                return;
+            }
+            if publish(
+                &config.swing_mode_command_topic,
+                &config.swing_mode_command_template,
+                json!(mode),
+            ) && config.optimistic_for(&config.swing_mode_state_topic)
+            {
+                attributes.swing_mode = Some(mode.clone());
+            }
+        }
+        ClimateCommand::SetPresetMode(mode) => {
+            if mode != PRESET_NONE && !config.preset_modes.contains(mode) {
+                warn!("Unsupported preset mode: {}", mode);
+                return;
+            }
+            if publish(
+                &config.preset_mode_command_topic,
+                &config.preset_mode_command_template,
+                json!(mode),
+            ) && config.optimistic_for(&config.preset_mode_state_topic)
+            {
+                attributes.preset_mode = (mode != PRESET_NONE).then(|| mode.clone());
+            }
+        }
+        ClimateCommand::TurnOn => {
+            if config.power_command_topic.is_none() {
+                warn!("Climate has no power_command_topic, set a hvac mode instead");
+                return;
+            }
+            publish(
+                &config.power_command_topic,
+                &config.power_command_template,
+                json!(config.payload_on()),
+            );
+        }
+        ClimateCommand::TurnOff => {
+            // Without a power topic turning off means switching to the `off` hvac mode
+            let (topic, template, value) = if config.power_command_topic.is_some() {
+                (
+                    &config.power_command_topic,
+                    &config.power_command_template,
+                    json!(config.payload_off()),
+                )
+            } else if config.modes.contains(&HvacMode::Off) {
+                (
+                    &config.mode_command_topic,
+                    &config.mode_command_template,
+                    json!(HvacMode::Off),
+                )
+            } else {
+                warn!("Climate does not support turning off");
+                return;
+            };
+            if publish(topic, template, value) && config.optimistic_for(&config.mode_state_topic) {
+                state.update(HvacMode::Off);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_climate_configuration() {
+    let json = r#"
+    {
+        "name": "Study",
+        "mode_command_topic": "study/ac/mode/set",
+        "mode_state_topic": "study/ac/mode/state",
+        "modes": ["off", "cool", "fan_only"],
+        "temperature_command_topic": "study/ac/temperature/set",
+        "current_temperature_topic": "study/ac/temperature/current",
+        "precision": 1.0,
+        "preset_modes": ["eco", "sleep"]
+    }
+    "#;
+    let config: MqttClimateConfiguration = serde_json::from_str(json).unwrap();
+
+    assert_eq!(
+        config.modes,
+        vec![HvacMode::Off, HvacMode::Cool, HvacMode::FanOnly]
+    );
+    assert_eq!(config.fan_modes, vec!["auto", "low", "medium", "high"]);
+    assert_eq!(
+        config.state_topics(),
+        vec!["study/ac/temperature/current", "study/ac/mode/state"]
+    );
+    assert!(!config.optimistic_for(&config.mode_state_topic));
+    assert!(config.optimistic_for(&config.temperature_state_topic));
+    assert_eq!(HvacMode::from_str("fan_only").unwrap(), HvacMode::FanOnly);
+    assert_eq!(
+        render_option(&None, b"sleep", &config.preset_modes),
+        Some(Some("sleep".to_string()))
+    );
+    assert_eq!(render_float(&None, b"21.5"), Some(21.5));
+}
@@ -81,4 +81,16 @@ pub const CONF_CONFIGURATION_URL: &str = "configuration_url";
 pub const CONF_OBJECT_ID: &str = "object_id";
 pub const CONF_SUPPORT_URL: &str = "support_url";
 
+pub const DEFAULT_PAYLOAD_ON: &str = "ON";
+pub const DEFAULT_PAYLOAD_OFF: &str = "OFF";
+pub const DEFAULT_PAYLOAD_ONLINE: &str = "online";
+pub const DEFAULT_PAYLOAD_OFFLINE: &str = "offline";
+pub const DEFAULT_PREFIX: &str = "homeassistant";
+pub const DEFAULT_CAPACITY: usize = 20;
+pub const DEFAULT_KEEPALIVE: u64 = 60;
+pub const DEFAULT_WS_PATH: &str = "/";
+pub const PROTOCOL_311: &str = "3.1.1";
+pub const PROTOCOL_5: &str = "5";
+pub const PAYLOAD_NONE: &str = "None";
+
 pub const DOMAIN: &str = "mqtt";
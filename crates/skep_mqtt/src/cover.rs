@@ -0,0 +1,467 @@
+use crate::{
+    constants::PAYLOAD_NONE,
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_variables, try_render_template, MQTTPlatformState, MqttPublisher},
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::Children;
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map};
+use skep_core::{
+    constants::{STATE_CLOSED, STATE_CLOSING, STATE_OPEN, STATE_OPENING, STATE_UNKNOWN},
+    states::State,
+};
+
+pub struct MqttCoverPlugin;
+
+impl Plugin for MqttCoverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_cover_state)
+        .observe(on_cover_command);
+    }
+}
+
+const DOMAIN: &str = "cover";
+
+const DEFAULT_PAYLOAD_OPEN: &str = "OPEN";
+const DEFAULT_PAYLOAD_CLOSE: &str = "CLOSE";
+const DEFAULT_PAYLOAD_STOP: &str = "STOP";
+const DEFAULT_POSITION_OPEN: i32 = 100;
+const DEFAULT_POSITION_CLOSED: i32 = 0;
+const DEFAULT_TILT_MIN: i32 = 0;
+const DEFAULT_TILT_MAX: i32 = 100;
+const DEFAULT_TILT_OPEN_POSITION: i32 = 100;
+const DEFAULT_TILT_CLOSED_POSITION: i32 = 0;
+const STATE_STOPPED: &str = "stopped";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Has<CoverAttributes>,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_state, has_attributes, opt_children) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttCoverConfiguration>(payload.payload.clone())
+        {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid cover config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            [&config.position_topic, &config.tilt_status_topic]
+                .into_iter()
+                .flatten()
+                .map(String::as_str),
+            config.qos.unwrap_or(0),
+        );
+        let mut cmds = commands.entity(entity);
+        cmds.insert((config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+        if !has_attributes {
+            cmds.insert(CoverAttributes::default());
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttCoverConfiguration {
+    pub command_topic: Option<String>,
+    pub state_topic: Option<String>,
+    pub value_template: Option<String>,
+    pub payload_open: Option<String>,
+    pub payload_close: Option<String>,
+    /// The command payload that stops the cover, set to `null` to disable the stop command.
+    #[serde(default = "default_payload_stop")]
+    pub payload_stop: Option<String>,
+    pub state_open: Option<String>,
+    pub state_opening: Option<String>,
+    pub state_closed: Option<String>,
+    pub state_closing: Option<String>,
+    pub state_stopped: Option<String>,
+    pub position_topic: Option<String>,
+    pub position_template: Option<String>,
+    pub position_open: Option<i32>,
+    pub position_closed: Option<i32>,
+    pub set_position_topic: Option<String>,
+    pub set_position_template: Option<String>,
+    pub tilt_command_topic: Option<String>,
+    pub tilt_command_template: Option<String>,
+    pub tilt_status_topic: Option<String>,
+    pub tilt_status_template: Option<String>,
+    pub tilt_min: Option<i32>,
+    pub tilt_max: Option<i32>,
+    pub tilt_closed_value: Option<i32>,
+    pub tilt_opened_value: Option<i32>,
+    pub tilt_optimistic: Option<bool>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+fn default_payload_stop() -> Option<String> {
+    Some(DEFAULT_PAYLOAD_STOP.to_string())
+}
+
+impl MqttCoverConfiguration {
+    pub fn payload_open(&self) -> &str {
+        self.payload_open.as_deref().unwrap_or(DEFAULT_PAYLOAD_OPEN)
+    }
+
+    pub fn payload_close(&self) -> &str {
+        self.payload_close
+            .as_deref()
+            .unwrap_or(DEFAULT_PAYLOAD_CLOSE)
+    }
+
+    pub fn position_open(&self) -> i32 {
+        self.position_open.unwrap_or(DEFAULT_POSITION_OPEN)
+    }
+
+    pub fn position_closed(&self) -> i32 {
+        self.position_closed.unwrap_or(DEFAULT_POSITION_CLOSED)
+    }
+
+    fn tilt_min(&self) -> i32 {
+        self.tilt_min.unwrap_or(DEFAULT_TILT_MIN)
+    }
+
+    fn tilt_max(&self) -> i32 {
+        self.tilt_max.unwrap_or(DEFAULT_TILT_MAX)
+    }
+
+    pub fn optimistic(&self) -> bool {
+        self.optimistic
+            .unwrap_or(self.state_topic.is_none() && self.position_topic.is_none())
+    }
+
+    pub fn tilt_optimistic(&self) -> bool {
+        self.tilt_optimistic
+            .unwrap_or(self.tilt_status_topic.is_none())
+    }
+
+    /// Map a rendered state payload to one of the cover `STATE_*` values.
+    pub fn parse_state(&self, payload: &str, position: Option<u8>) -> Option<&'static str> {
+        if payload == self.state_open.as_deref().unwrap_or(STATE_OPEN) {
+            Some(STATE_OPEN)
+        } else if payload == self.state_opening.as_deref().unwrap_or(STATE_OPENING) {
+            Some(STATE_OPENING)
+        } else if payload == self.state_closed.as_deref().unwrap_or(STATE_CLOSED) {
+            Some(STATE_CLOSED)
+        } else if payload == self.state_closing.as_deref().unwrap_or(STATE_CLOSING) {
+            Some(STATE_CLOSING)
+        } else if payload == self.state_stopped.as_deref().unwrap_or(STATE_STOPPED) {
+            match position {
+                Some(0) => Some(STATE_CLOSED),
+                _ => Some(STATE_OPEN),
+            }
+        } else if payload == PAYLOAD_NONE {
+            Some(STATE_UNKNOWN)
+        } else {
+            None
+        }
+    }
+
+    /// Convert a device position to a 0-100 percentage.
+    pub fn position_to_percentage(&self, position: f64) -> u8 {
+        to_percentage(
+            position,
+            self.position_closed() as f64,
+            self.position_open() as f64,
+        )
+    }
+
+    /// Convert a 0-100 percentage to a device position.
+    pub fn percentage_to_position(&self, percentage: u8) -> i32 {
+        from_percentage(percentage, self.position_closed(), self.position_open())
+    }
+
+    pub fn tilt_to_percentage(&self, tilt: f64) -> u8 {
+        to_percentage(tilt, self.tilt_min() as f64, self.tilt_max() as f64)
+    }
+
+    pub fn percentage_to_tilt(&self, percentage: u8) -> i32 {
+        from_percentage(percentage, self.tilt_min(), self.tilt_max())
+    }
+}
+
+pub(crate) fn to_percentage(value: f64, min: f64, max: f64) -> u8 {
+    if max == min {
+        return 0;
+    }
+    ((value - min) / (max - min) * 100.0)
+        .round()
+        .clamp(0.0, 100.0) as u8
+}
+
+pub(crate) fn from_percentage(percentage: u8, min: i32, max: i32) -> i32 {
+    (min as f64 + (max - min) as f64 * percentage as f64 / 100.0).round() as i32
+}
+
+/// Current cover position and tilt as a 0-100 percentage, 0 being closed.
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct CoverAttributes {
+    pub current_position: Option<u8>,
+    pub current_tilt_position: Option<u8>,
+}
+
+/// Commands accepted by an MQTT cover entity, triggered with the cover as target.
+#[derive(Debug, Event, Clone, Copy, PartialEq, Eq)]
+pub enum CoverCommand {
+    Open,
+    Close,
+    Stop,
+    /// Move the cover to a 0-100 position
+    SetPosition(u8),
+    OpenTilt,
+    CloseTilt,
+    /// Move the cover tilt to a 0-100 position
+    SetTiltPosition(u8),
+}
+
+fn handle_cover_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_cover: Query<(&MqttCoverConfiguration, &mut State, &mut CoverAttributes)>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_cover.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    let payload = &topic_message.event().payload;
+
+    if config.state_topic.as_deref() == Some(topic) {
+        let value = try_render_template(&config.value_template, payload).unwrap_or_default();
+        match config.parse_state(&value, attributes.current_position) {
+            Some(new_state) => state.update(new_state),
+            None if value.is_empty() => state.update(STATE_UNKNOWN),
+            None => warn!("Ignoring cover state payload {:?} on {}", value, topic),
+        }
+    }
+
+    if config.position_topic.as_deref() == Some(topic) {
+        let value = try_render_template(&config.position_template, payload).unwrap_or_default();
+        match value.trim().parse::<f64>() {
+            Ok(position) => {
+                let percentage = config.position_to_percentage(position);
+                attributes.current_position = Some(percentage);
+                if config.state_topic.is_none() {
+                    state.update(if percentage == 0 {
+                        STATE_CLOSED
+                    } else {
+                        STATE_OPEN
+                    });
+                }
+            }
+            Err(_) => warn!("Ignoring cover position payload {:?} on {}", value, topic),
+        }
+    }
+
+    if config.tilt_status_topic.as_deref() == Some(topic) {
+        let value = try_render_template(&config.tilt_status_template, payload).unwrap_or_default();
+        match value.trim().parse::<f64>() {
+            Ok(tilt) => attributes.current_tilt_position = Some(config.tilt_to_percentage(tilt)),
+            Err(_) => warn!("Ignoring cover tilt payload {:?} on {}", value, topic),
+        }
+    }
+}
+
+fn on_cover_command(
+    trigger: Trigger<CoverCommand>,
+    publisher: MqttPublisher,
+    mut q_cover: Query<(&MqttCoverConfiguration, &mut State, &mut CoverAttributes)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state, mut attributes)) = q_cover.get_mut(entity) else {
+        return;
+    };
+    let command = *trigger.event();
+
+    let message = match command {
+        CoverCommand::Open => config
+            .command_topic
+            .clone()
+            .map(|topic| (topic, config.payload_open().to_string())),
+        CoverCommand::Close => config
+            .command_topic
+            .clone()
+            .map(|topic| (topic, config.payload_close().to_string())),
+        CoverCommand::Stop => config
+            .command_topic
+            .clone()
+            .zip(config.payload_stop.clone()),
+        CoverCommand::SetPosition(percentage) => {
+            let position = config.percentage_to_position(percentage);
+            let payload = match &config.set_position_template {
+                Some(template) => {
+                    let mut variables = Map::new();
+                    variables.insert("value".to_string(), json!(position));
+                    variables.insert("position".to_string(), json!(percentage));
+                    variables.insert(
+                        "tilt_position".to_string(),
+                        json!(attributes.current_tilt_position),
+                    );
+                    match try_render_command_variables(template, &variables) {
+                        Ok(payload) => payload,
+                        Err(e) => {
+                            warn!("Failed to render cover position: {}", e);
+                            return;
+                        }
+                    }
+                }
+                None => position.to_string(),
+            };
+            config
+                .set_position_topic
+                .clone()
+                .map(|topic| (topic, payload))
+        }
+        CoverCommand::OpenTilt => {
+            let tilt = config
+                .tilt_opened_value
+                .unwrap_or(DEFAULT_TILT_OPEN_POSITION);
+            return command_tilt(entity, &publisher, config, &mut attributes, tilt);
+        }
+        CoverCommand::CloseTilt => {
+            let tilt = config
+                .tilt_closed_value
+                .unwrap_or(DEFAULT_TILT_CLOSED_POSITION);
+            return command_tilt(entity, &publisher, config, &mut attributes, tilt);
+        }
+        CoverCommand::SetTiltPosition(percentage) => {
+            let tilt = config.percentage_to_tilt(percentage);
+            return command_tilt(entity, &publisher, config, &mut attributes, tilt);
+        }
+    };
+
+    if let Some((topic, payload)) = message {
+        if let Err(e) = publisher.publish(entity, &topic, config.qos, config.retain, payload) {
+            warn!("Failed to publish cover command: {}", e);
+            return;
+        }
+    }
+
+    if config.optimistic() {
+        match command {
+            CoverCommand::Open => {
+                state.update(STATE_OPEN);
+                attributes.current_position = Some(100);
+            }
+            CoverCommand::Close => {
+                state.update(STATE_CLOSED);
+                attributes.current_position = Some(0);
+            }
+            CoverCommand::SetPosition(percentage) => {
+                state.update(if percentage == 0 {
+                    STATE_CLOSED
+                } else {
+                    STATE_OPEN
+                });
+                attributes.current_position = Some(percentage);
+            }
+            CoverCommand::Stop
+            | CoverCommand::OpenTilt
+            | CoverCommand::CloseTilt
+            | CoverCommand::SetTiltPosition(_) => {}
+        }
+    }
+}
+
+/// Publish a tilt command, the tilt is updated optimistically without a tilt status topic.
+fn command_tilt(
+    entity: Entity,
+    publisher: &MqttPublisher,
+    config: &MqttCoverConfiguration,
+    attributes: &mut CoverAttributes,
+    tilt: i32,
+) {
+    let payload = match &config.tilt_command_template {
+        Some(template) => {
+            let mut variables = Map::new();
+            variables.insert("value".to_string(), json!(tilt));
+            variables.insert("position".to_string(), json!(attributes.current_position));
+            match try_render_command_variables(template, &variables) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    warn!("Failed to render cover tilt: {}", e);
+                    return;
+                }
+            }
+        }
+        None => tilt.to_string(),
+    };
+    if let Some(topic) = &config.tilt_command_topic {
+        if let Err(e) = publisher.publish(entity, topic, config.qos, config.retain, payload) {
+            warn!("Failed to publish cover tilt command: {}", e);
+            return;
+        }
+    }
+    if config.tilt_optimistic() {
+        attributes.current_tilt_position = Some(config.tilt_to_percentage(tilt as f64));
+    }
+}
+
+#[test]
+fn test_cover_configuration() {
+    let json = r#"
+    {
+        "command_topic": "blinds/set",
+        "state_topic": "blinds/state",
+        "position_topic": "blinds/position",
+        "set_position_topic": "blinds/position/set",
+        "position_open": 255,
+        "position_closed": 0,
+        "payload_stop": null,
+        "state_stopped": "idle"
+    }
+    "#;
+    let config: MqttCoverConfiguration = serde_json::from_str(json).unwrap();
+
+    assert!(!config.optimistic());
+    assert!(config.payload_stop.is_none());
+    assert_eq!(config.position_to_percentage(255.0), 100);
+    assert_eq!(config.position_to_percentage(51.0), 20);
+    assert_eq!(config.percentage_to_position(50), 128);
+    assert_eq!(config.parse_state("opening", None), Some(STATE_OPENING));
+    assert_eq!(config.parse_state("idle", Some(0)), Some(STATE_CLOSED));
+    assert_eq!(config.parse_state("idle", Some(40)), Some(STATE_OPEN));
+
+    let config: MqttCoverConfiguration = serde_json::from_str("{}").unwrap();
+    assert!(config.optimistic());
+    assert_eq!(config.payload_stop.as_deref(), Some("STOP"));
+}
@@ -0,0 +1,185 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_template, MQTTPlatformState},
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::{Children, Parent};
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use serde::{Deserialize, Serialize};
+use skep_core::device::Device;
+
+pub struct MqttDeviceAutomationPlugin;
+
+impl Plugin for MqttDeviceAutomationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_device_automation_message);
+    }
+}
+
+const DOMAIN: &str = "device_automation";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_children) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttDeviceAutomationConfiguration>(
+            payload.payload.clone(),
+        ) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid device_automation config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            [config.topic.as_str()],
+            config.qos.unwrap_or(0),
+        );
+        commands.entity(entity).insert((config, MQTTPlatformState));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AutomationType {
+    Trigger,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttDeviceAutomationConfiguration {
+    pub automation_type: AutomationType,
+    pub topic: String,
+    /// The trigger type, e.g. `button_short_press`
+    #[serde(rename = "type")]
+    pub trigger_type: String,
+    /// The trigger subtype, e.g. `button_1`
+    pub subtype: String,
+    /// Only fire when the rendered payload matches, any payload fires when not set
+    pub payload: Option<String>,
+    pub value_template: Option<String>,
+    pub qos: Option<u8>,
+}
+
+impl MqttDeviceAutomationConfiguration {
+    /// Whether a rendered payload fires this trigger.
+    pub fn matches(&self, value: &str) -> bool {
+        self.payload
+            .as_deref()
+            .map_or(true, |payload| payload == value)
+    }
+}
+
+/// Fired with the [`Device`] of a `device_automation` trigger as target.
+#[derive(Debug, Event, Clone, PartialEq, Eq)]
+pub struct DeviceTrigger {
+    pub trigger_type: String,
+    pub subtype: String,
+    pub payload: String,
+}
+
+fn handle_device_automation_message(
+    topic_message: Trigger<TopicMessage>,
+    mut commands: Commands,
+    q_automation: Query<(&MqttDeviceAutomationConfiguration, &Parent)>,
+    q_device: Query<(), With<Device>>,
+) {
+    let entity = topic_message.entity();
+    let Ok((config, parent)) = q_automation.get(entity) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.topic != topic {
+        return;
+    }
+    let value = match try_render_template(&config.value_template, &topic_message.event().payload) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!(
+                "Failed to render device_automation payload on {}: {}",
+                topic, e
+            );
+            return;
+        }
+    };
+    if !config.matches(&value) {
+        return;
+    }
+    if !q_device.contains(parent.get()) {
+        warn!("device_automation {:?} is not attached to a device", entity);
+        return;
+    }
+
+    debug!(
+        "device trigger {} {}: {}",
+        config.trigger_type, config.subtype, value
+    );
+    commands.trigger_targets(
+        DeviceTrigger {
+            trigger_type: config.trigger_type.clone(),
+            subtype: config.subtype.clone(),
+            payload: value,
+        },
+        parent.get(),
+    );
+}
+
+#[test]
+fn test_device_automation_configuration() {
+    let json = r#"
+    {
+        "automation_type": "trigger",
+        "topic": "zigbee2mqtt/wall_switch/action",
+        "type": "button_short_press",
+        "subtype": "button_1",
+        "payload": "single_left"
+    }
+    "#;
+    let config: MqttDeviceAutomationConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.automation_type, AutomationType::Trigger);
+    assert_eq!(config.trigger_type, "button_short_press");
+    assert!(config.matches("single_left"));
+    assert!(!config.matches("double_left"));
+
+    let json = r#"
+    {
+        "automation_type": "action",
+        "topic": "zigbee2mqtt/wall_switch/action",
+        "type": "button_short_press",
+        "subtype": "button_1"
+    }
+    "#;
+    assert!(serde_json::from_str::<MqttDeviceAutomationConfiguration>(json).is_err());
+}
@@ -0,0 +1,268 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_template, MQTTAttributesBlocked, MQTTPlatformState},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use skep_core::{
+    constants::{STATE_HOME, STATE_NOT_HOME, STATE_UNKNOWN},
+    states::State,
+    zone::HomeZone,
+};
+use std::collections::HashSet;
+
+lazy_static! {
+    static ref MQTT_DEVICE_TRACKER_ATTRIBUTES_BLOCKED: HashSet<&'static str> =
+        HashSet::from(["battery_level", "gps_accuracy", "latitude", "longitude"]);
+}
+
+pub struct MqttDeviceTrackerPlugin;
+
+impl Plugin for MqttDeviceTrackerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_device_tracker_state);
+    }
+}
+
+const DOMAIN: &str = "device_tracker";
+
+const DEFAULT_PAYLOAD_HOME: &str = "home";
+const DEFAULT_PAYLOAD_NOT_HOME: &str = "not_home";
+const DEFAULT_PAYLOAD_RESET: &str = "None";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Has<DeviceTrackerAttributes>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state, has_attributes) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttDeviceTrackerConfiguration>(payload.payload.clone())
+            {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid device_tracker config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        if !has_attributes {
+            cmds.insert(DeviceTrackerAttributes::default());
+        }
+        cmds.insert((
+            config,
+            MQTTPlatformState,
+            MQTTAttributesBlocked(&MQTT_DEVICE_TRACKER_ATTRIBUTES_BLOCKED),
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceType {
+    #[default]
+    Gps,
+    Router,
+    Bluetooth,
+    BluetoothLe,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttDeviceTrackerConfiguration {
+    pub state_topic: Option<String>,
+    pub value_template: Option<String>,
+    pub payload_home: Option<String>,
+    pub payload_not_home: Option<String>,
+    /// Resets the location name, the state is then resolved from the GPS position.
+    pub payload_reset: Option<String>,
+    pub source_type: Option<SourceType>,
+    /// Receives a JSON object with `latitude`, `longitude` and `gps_accuracy`, OwnTracks
+    /// `lat`, `lon` and `acc` are accepted as well.
+    pub json_attributes_topic: Option<String>,
+    pub json_attributes_template: Option<String>,
+    pub qos: Option<u8>,
+}
+
+impl MqttDeviceTrackerConfiguration {
+    pub fn payload_home(&self) -> &str {
+        self.payload_home.as_deref().unwrap_or(DEFAULT_PAYLOAD_HOME)
+    }
+
+    pub fn payload_not_home(&self) -> &str {
+        self.payload_not_home
+            .as_deref()
+            .unwrap_or(DEFAULT_PAYLOAD_NOT_HOME)
+    }
+
+    pub fn payload_reset(&self) -> &str {
+        self.payload_reset
+            .as_deref()
+            .unwrap_or(DEFAULT_PAYLOAD_RESET)
+    }
+}
+
+/// Last reported location of a tracker.
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct DeviceTrackerAttributes {
+    /// Location reported on the state topic, e.g. `home` or a zone name
+    pub location_name: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Accuracy of the GPS position in meters
+    pub gps_accuracy: Option<f64>,
+    pub battery_level: Option<u8>,
+}
+
+impl DeviceTrackerAttributes {
+    /// Update the position from a JSON attributes payload.
+    fn update_from_json(&mut self, object: &Map<String, Value>) {
+        let number = |keys: &[&str]| keys.iter().find_map(|key| object.get(*key)?.as_f64());
+        if let (Some(latitude), Some(longitude)) =
+            (number(&["latitude", "lat"]), number(&["longitude", "lon"]))
+        {
+            self.latitude = Some(latitude);
+            self.longitude = Some(longitude);
+            self.gps_accuracy = number(&["gps_accuracy", "acc"]);
+        }
+        if let Some(battery_level) = number(&["battery_level", "batt"]) {
+            self.battery_level = Some(battery_level.clamp(0.0, 100.0) as u8);
+        }
+    }
+
+    /// The tracker state, a reported location name wins over the GPS position.
+    pub fn resolve_state(&self, home_zone: Option<&HomeZone>) -> String {
+        if let Some(location_name) = &self.location_name {
+            return location_name.clone();
+        }
+        match (self.latitude, self.longitude) {
+            // Without a home zone a GPS position can't be resolved
+            (Some(latitude), Some(longitude)) => match home_zone {
+                Some(zone) => {
+                    let accuracy = self.gps_accuracy.unwrap_or_default();
+                    if zone.contains(latitude, longitude, accuracy) {
+                        STATE_HOME.to_string()
+                    } else {
+                        STATE_NOT_HOME.to_string()
+                    }
+                }
+                None => STATE_UNKNOWN.to_string(),
+            },
+            _ => STATE_UNKNOWN.to_string(),
+        }
+    }
+}
+
+fn handle_device_tracker_state(
+    topic_message: Trigger<TopicMessage>,
+    home_zone: Option<Res<HomeZone>>,
+    mut q_tracker: Query<(
+        &MqttDeviceTrackerConfiguration,
+        &mut State,
+        &mut DeviceTrackerAttributes,
+    )>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_tracker.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    let payload = &topic_message.event().payload[..];
+    let mut updated = false;
+
+    if config.state_topic.as_deref() == Some(topic) {
+        let value = try_render_template(&config.value_template, payload).unwrap_or_default();
+        attributes.location_name = if value == config.payload_home() {
+            Some(STATE_HOME.to_string())
+        } else if value == config.payload_not_home() {
+            Some(STATE_NOT_HOME.to_string())
+        } else if value == config.payload_reset() || value.is_empty() {
+            None
+        } else {
+            Some(value)
+        };
+        updated = true;
+    }
+
+    if config.json_attributes_topic.as_deref() == Some(topic) {
+        let value =
+            try_render_template(&config.json_attributes_template, payload).unwrap_or_default();
+        match serde_json::from_str::<Map<String, Value>>(&value) {
+            Ok(object) => {
+                attributes.update_from_json(&object);
+                updated = true;
+            }
+            Err(e) => warn!("Ignoring device_tracker attributes on {}: {}", topic, e),
+        }
+    }
+
+    if updated {
+        state.update(attributes.resolve_state(home_zone.as_deref()));
+    }
+}
+
+#[test]
+fn test_device_tracker_configuration() {
+    let json = r#"
+    {
+        "state_topic": "owntracks/phone/state",
+        "json_attributes_topic": "owntracks/phone",
+        "payload_home": "HOME",
+        "source_type": "gps"
+    }
+    "#;
+    let config: MqttDeviceTrackerConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.payload_home(), "HOME");
+    assert_eq!(config.payload_reset(), "None");
+    assert_eq!(config.source_type, Some(SourceType::Gps));
+
+    let zone = HomeZone::new(52.3731, 4.8922);
+    let mut attributes = DeviceTrackerAttributes::default();
+    assert_eq!(attributes.resolve_state(Some(&zone)), STATE_UNKNOWN);
+
+    let object = serde_json::from_str::<Map<String, Value>>(
+        r#"{"_type": "location", "lat": 52.3732, "lon": 4.8921, "acc": 15, "batt": 80}"#,
+    )
+    .unwrap();
+    attributes.update_from_json(&object);
+    assert_eq!(attributes.battery_level, Some(80));
+    assert_eq!(attributes.resolve_state(Some(&zone)), STATE_HOME);
+    assert_eq!(attributes.resolve_state(None), STATE_UNKNOWN);
+    assert_eq!(
+        attributes.resolve_state(Some(&HomeZone::new(51.9225, 4.4792))),
+        STATE_NOT_HOME
+    );
+
+    attributes.location_name = Some("work".to_string());
+    assert_eq!(attributes.resolve_state(Some(&zone)), "work");
+}
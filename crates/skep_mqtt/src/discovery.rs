@@ -7,12 +7,15 @@ use bevy_ecs::prelude::*;
 use bevy_log::{debug, trace, warn};
 use bevy_mqtt::{
     rumqttc::{QoS, SubscribeFilter},
-    MqttClient, MqttClientConnected, MqttPublishPacket,
+    MqttClient, MqttClientConnected, MqttPublishPacket, SubscribeTopic,
 };
 
 use crate::{
     constants::DOMAIN,
-    entity::{AvailabilityConfig, MQTTAvailability, MQTTAvailabilityConfiguration},
+    entity::{
+        AvailabilityConfig, MQTTAvailability, MQTTAvailabilityConfiguration,
+        MQTTJsonAttributesConfiguration,
+    },
     subscription::MQTTStateSubscription,
 };
 use bevy_core::Name;
@@ -22,15 +25,17 @@ use bevy_ecs::{
     system::EntityCommands,
     world::DeferredWorld,
 };
-use bevy_hierarchy::{BuildChildren, ChildBuilder, Children, Parent};
+use bevy_hierarchy::{
+    BuildChildren, ChildBuilder, Children, DespawnRecursiveExt, HierarchyQueryExt, Parent,
+};
 use bevy_reflect::Reflect;
-use bevy_utils::{hashbrown::HashSet, HashMap};
+use bevy_utils::{HashMap, HashSet};
 use chrono::Utc;
 use regex::{Error, Regex};
 use serde::Deserialize;
 use serde_json::{json, Map, Value};
 use skep_core::{
-    constants::EntityCategory,
+    constants::{EntityCategory, CONF_DEVICE, CONF_PLATFORM},
     device::{Device, DeviceResource},
     helper::{
         device_registry::{DeviceInfo, DeviceSpec},
@@ -40,9 +45,11 @@ use skep_core::{
     states::{StateAttributes, StateUpdateTime},
     typing::SetupConfigEntry,
 };
+use slugify::slugify;
 use std::{
     collections::VecDeque,
     fmt::{Display, Formatter},
+    hash::{DefaultHasher, Hash, Hasher},
     str::FromStr,
 };
 use strum_macros::{Display, EnumString};
@@ -53,6 +60,32 @@ use strum_macros::{Display, EnumString};
 pub enum MQTTSupportComponent {
     Sensor,
     BinarySensor,
+    Switch,
+    Light,
+    Cover,
+    Climate,
+    Fan,
+    Humidifier,
+    Lock,
+    Valve,
+    Number,
+    Select,
+    Text,
+    Button,
+    Scene,
+    Siren,
+    AlarmControlPanel,
+    WaterHeater,
+    Vacuum,
+    LawnMower,
+    DeviceTracker,
+    DeviceAutomation,
+    Event,
+    Tag,
+    Update,
+    Image,
+    Camera,
+    Notify,
 }
 
 #[derive(Debug, Reflect, Hash, PartialEq, Eq, Clone)]
@@ -67,6 +100,18 @@ impl Display for MQTTDiscoveryHash {
     }
 }
 
+impl MQTTDiscoveryHash {
+    /// The entity id, `<component>.<discovery_id>` with a `node_id object_id` discovery id
+    /// slugified to `node_id_object_id`.
+    pub fn entity_id(&self) -> String {
+        format!(
+            "{}.{}",
+            self.component,
+            slugify!(&self.discovery_id, separator = "_")
+        )
+    }
+}
+
 impl Component for MQTTDiscoveryHash {
     const STORAGE_TYPE: StorageType = StorageType::Table;
 
@@ -77,6 +122,15 @@ impl Component for MQTTDiscoveryHash {
                 "{}.{}",
                 discovery_hash.component, discovery_hash.discovery_id
             ));
+            let entity_id = discovery_hash.entity_id();
+            match world.get_mut::<SkepEntityComponent>(entity) {
+                Some(mut skep_entity) => skep_entity.entity_id = Some(entity_id),
+                None => {
+                    let mut skep_entity = SkepEntityComponent::default();
+                    skep_entity.entity_id = Some(entity_id);
+                    world.commands().entity(entity).insert(skep_entity);
+                }
+            }
             let mut commands = world.commands();
             commands.entity(entity).insert(name);
         });
@@ -97,7 +151,7 @@ pub fn sub_default_topic(
 ) {
     for (mqtt_platform, mqtt_client) in mqtt_clients.iter() {
         let mut subs = vec![];
-        for component in SUPPORTED_COMPONENTS {
+        for component in SUPPORTED_COMPONENTS.iter().chain(&[DEVICE_DISCOVERY]) {
             subs.push(SubscribeFilter::new(
                 format!("{}/{}/+/config", mqtt_platform.discovery_prefix, component),
                 QoS::AtMostOnce,
@@ -159,6 +213,14 @@ pub struct MQTTDiscoveryNew(pub MQTTDiscoveryPayload);
 #[derive(Debug, Event, Deref, DerefMut)]
 pub struct MQTTDiscoveryUpdate(pub MQTTDiscoveryPayload);
 
+/// Setup of a newly discovered entity has finished, its queued configs can be applied.
+#[derive(Debug, Event, Deref, DerefMut)]
+pub struct MQTTDiscoveryDone(pub MQTTDiscoveryHash);
+
+/// An empty config payload was published for an already discovered entity.
+#[derive(Debug, Event, Deref, DerefMut)]
+pub struct MQTTDiscoveryRemove(pub MQTTDiscoveryHash);
+
 pub(crate) fn on_mqtt_message_received(
     mut publish_ev: EventReader<MqttPublishPacket>,
     mut query: Query<(&mut SkepMqttPlatform,)>,
@@ -177,65 +239,101 @@ pub(crate) fn on_mqtt_message_received(
                 "",
                 1,
             );
-            if let (Ok((component, node_id, object_id)), Ok(discovery_payload)) = (
-                parse_topic_config(&topic_trimmed),
-                handle_discovery_message(&payload),
-            ) {
+            if let Ok((component, node_id, object_id)) = parse_topic_config(&topic_trimmed) {
                 let discovery_id = if let Some(node_id) = node_id {
                     format!("{} {}", node_id, object_id)
                 } else {
                     object_id.clone()
                 };
+
+                if component == DEVICE_DISCOVERY {
+                    let components = if payload.is_empty() {
+                        vec![]
+                    } else {
+                        match handle_device_discovery_message(&payload) {
+                            Ok(components) => components,
+                            Err(e) => {
+                                warn!("Invalid device discovery payload on {}: {}", topic, e);
+                                continue;
+                            }
+                        }
+                    };
+
+                    let mut discovery_hashes = HashSet::default();
+                    for (component, component_id, discovery_payload) in components {
+                        let discovery_hash = MQTTDiscoveryHash {
+                            component,
+                            discovery_id: format!("{} {}", discovery_id, component_id),
+                        };
+                        discovery_hashes.insert(discovery_hash.clone());
+                        discover(
+                            &mut mqtt_platform,
+                            &mut commands,
+                            platform_entity,
+                            MQTTDiscoveryPayload {
+                                topic: topic_trimmed.to_string(),
+                                hash: discovery_hash,
+                                payload: Value::from(discovery_payload),
+                                platform: "mqtt".to_string(),
+                            },
+                        );
+                    }
+
+                    // Components dropped from the map are removed like an empty config
+                    let previous = if discovery_hashes.is_empty() {
+                        mqtt_platform
+                            .discovery_device_components
+                            .remove(&discovery_id)
+                    } else {
+                        mqtt_platform
+                            .discovery_device_components
+                            .insert(discovery_id, discovery_hashes.clone())
+                    };
+                    for discovery_hash in previous.unwrap_or_default() {
+                        if !discovery_hashes.contains(&discovery_hash) {
+                            remove_discovered(
+                                &mut mqtt_platform,
+                                &mut commands,
+                                platform_entity,
+                                discovery_hash,
+                            );
+                        }
+                    }
+                    continue;
+                }
+
                 let discovery_hash = MQTTDiscoveryHash {
                     component: component.clone(),
                     discovery_id: discovery_id.clone(),
                 };
-
-                let discovery_payload = MQTTDiscoveryPayload {
-                    topic: topic_trimmed.to_string(),
-                    hash: discovery_hash.clone(),
-                    payload: Value::from(discovery_payload),
-                    platform: "mqtt".to_string(),
-                };
-
-                if let Some(pending_discovered) = mqtt_platform
-                    .discovery_pending_discovered
-                    .get_mut(&discovery_hash)
-                {
-                    pending_discovered.pending.push_front(discovery_payload);
-                    debug!(
-                        "Component has already been discovered: {}, queueing update",
-                        discovery_hash
+                if payload.is_empty() {
+                    remove_discovered(
+                        &mut mqtt_platform,
+                        &mut commands,
+                        platform_entity,
+                        discovery_hash,
                     );
                     continue;
                 }
+                let discovery_payload = match handle_discovery_message(&payload) {
+                    Ok(discovery_payload) => discovery_payload,
+                    Err(e) => {
+                        warn!("Invalid discovery payload on {}: {}", topic, e);
+                        continue;
+                    }
+                };
 
-                let already_discovered = mqtt_platform
-                    .discovery_already_discovered
-                    .contains(&discovery_hash);
-                if already_discovered
-                    && !mqtt_platform
-                        .discovery_pending_discovered
-                        .contains_key(&discovery_hash)
-                {
-                    mqtt_platform
-                        .discovery_already_discovered
-                        .insert(discovery_hash.clone());
-                }
-
-                if already_discovered {
-                    debug!(
-                        "Component has already been discovered: {}, sending update",
-                        discovery_hash
-                    );
-                    commands
-                        .trigger_targets(MQTTDiscoveryUpdate(discovery_payload), platform_entity);
-                } else {
-                    mqtt_platform
-                        .discovery_already_discovered
-                        .insert(discovery_hash.clone());
-                    commands.trigger_targets(MQTTDiscoveryNew(discovery_payload), platform_entity);
-                }
+                discover(
+                    &mut mqtt_platform,
+                    &mut commands,
+                    platform_entity,
+                    MQTTDiscoveryPayload {
+                        topic: topic_trimmed.to_string(),
+                        hash: discovery_hash,
+                        payload: Value::from(discovery_payload),
+                        platform: "mqtt".to_string(),
+                    },
+                );
             }
         } else {
             warn!("MqttPlatform not found {:?}", packet.topic);
@@ -243,6 +341,84 @@ pub(crate) fn on_mqtt_message_received(
     }
 }
 
+/// Create or update the entity of a discovery payload, or queue it while the entity is pending.
+pub(crate) fn discover(
+    mqtt_platform: &mut SkepMqttPlatform,
+    commands: &mut Commands,
+    platform_entity: Entity,
+    discovery_payload: MQTTDiscoveryPayload,
+) {
+    let discovery_hash = discovery_payload.hash.clone();
+    let payload_hash = hash_discovery_payload(&discovery_payload.payload);
+    if mqtt_platform.discovery_payload_hashes.get(&discovery_hash) == Some(&payload_hash) {
+        trace!("Config of {} did not change, skipping", discovery_hash);
+        return;
+    }
+    mqtt_platform
+        .discovery_payload_hashes
+        .insert(discovery_hash.clone(), payload_hash);
+
+    if let Some(pending_discovered) = mqtt_platform
+        .discovery_pending_discovered
+        .get_mut(&discovery_hash)
+    {
+        pending_discovered.pending.push_front(discovery_payload);
+        debug!(
+            "Component has already been discovered: {}, queueing update",
+            discovery_hash
+        );
+        return;
+    }
+
+    if mqtt_platform
+        .discovery_already_discovered
+        .contains(&discovery_hash)
+    {
+        debug!(
+            "Component has already been discovered: {}, sending update",
+            discovery_hash
+        );
+        commands.trigger_targets(MQTTDiscoveryUpdate(discovery_payload), platform_entity);
+    } else {
+        mqtt_platform
+            .discovery_already_discovered
+            .insert(discovery_hash.clone());
+        mqtt_platform
+            .discovery_pending_discovered
+            .insert(discovery_hash, PendingDiscovered::default());
+        commands.trigger_targets(MQTTDiscoveryNew(discovery_payload), platform_entity);
+    }
+}
+
+fn hash_discovery_payload(payload: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    payload.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Forget a discovered entity and remove it, after its config was cleared.
+fn remove_discovered(
+    mqtt_platform: &mut SkepMqttPlatform,
+    commands: &mut Commands,
+    platform_entity: Entity,
+    discovery_hash: MQTTDiscoveryHash,
+) {
+    mqtt_platform
+        .discovery_pending_discovered
+        .remove(&discovery_hash);
+    mqtt_platform.discovered.remove(&discovery_hash);
+    mqtt_platform
+        .discovery_payload_hashes
+        .remove(&discovery_hash);
+    if mqtt_platform
+        .discovery_already_discovered
+        .remove(&discovery_hash)
+    {
+        debug!("Removing component: {}", discovery_hash);
+        commands.trigger_targets(MQTTDiscoveryRemove(discovery_hash), platform_entity);
+    }
+}
+
 pub(crate) fn setup_new_entity_from_discovery(
     trigger: Trigger<MQTTDiscoveryNew>,
     mut commands: Commands,
@@ -314,6 +490,39 @@ pub(crate) fn setup_new_entity_from_discovery(
             }
         }
     }
+
+    // Queued after the spawn commands above, so the entity exists once setup is done
+    commands.trigger_targets(
+        MQTTDiscoveryDone(discovery_payload.hash.clone()),
+        component_entity,
+    );
+}
+
+/// Leave the in progress state of a discovered entity and apply the newest config that was
+/// received during its setup.
+pub(crate) fn finish_pending_discovery(
+    trigger: Trigger<MQTTDiscoveryDone>,
+    mut commands: Commands,
+    mut q_platform: Query<&mut SkepMqttPlatform>,
+) {
+    let platform_entity = trigger.entity();
+    let Ok(mut mqtt_platform) = q_platform.get_mut(platform_entity) else {
+        return;
+    };
+    let Some(mut pending_discovered) = mqtt_platform
+        .discovery_pending_discovered
+        .remove(&trigger.event().0)
+    else {
+        return;
+    };
+    if let Some(discovery_payload) = pending_discovered.pending.pop_front() {
+        debug!(
+            "Applying queued config of {}, dropping {} older",
+            trigger.event().0,
+            pending_discovered.pending.len()
+        );
+        commands.trigger_targets(MQTTDiscoveryUpdate(discovery_payload), platform_entity);
+    }
 }
 
 pub(crate) fn update_entity_from_discovery(
@@ -332,6 +541,62 @@ pub(crate) fn update_entity_from_discovery(
     }
 }
 
+/// Despawn the entity of a removed discovery config, unsubscribe the topics no other entity uses
+/// anymore and despawn its device once it has no entities left.
+pub(crate) fn remove_entity_from_discovery(
+    trigger: Trigger<MQTTDiscoveryRemove>,
+    mut commands: Commands,
+    q_client: Query<&MqttClient>,
+    q_entities: Query<(Entity, &MQTTDiscoveryHash, Option<&Children>)>,
+    q_parent: Query<&Parent>,
+    q_devices: Query<&Children, With<Device>>,
+    q_topics: Query<(Entity, &SubscribeTopic)>,
+) {
+    let platform_entity = trigger.entity();
+    let discovery_hash = trigger.event();
+    let Some((entity, _, opt_children)) = q_entities.iter().find(|(entity, hash, _)| {
+        *hash == discovery_hash
+            && q_parent
+                .iter_ancestors(*entity)
+                .any(|ancestor| ancestor == platform_entity)
+    }) else {
+        return;
+    };
+    debug!("remove_entity_from_discovery: {}", discovery_hash);
+
+    let removed: HashSet<Entity> = opt_children
+        .map(|children| children.iter().copied().collect())
+        .unwrap_or_default();
+    let still_used: HashSet<&str> = q_topics
+        .iter()
+        .filter(|(child, _)| !removed.contains(child))
+        .map(|(_, sub_topic)| sub_topic.topic())
+        .collect();
+    if let Ok(client) = q_client.get(platform_entity) {
+        for (_, sub_topic) in q_topics.iter_many(&removed) {
+            if !still_used.contains(sub_topic.topic()) {
+                if let Err(e) = client.unsubscribe(sub_topic.topic()) {
+                    warn!("Failed to unsubscribe {}: {}", sub_topic.topic(), e);
+                }
+            }
+        }
+    }
+
+    match q_parent.get(entity).map(|parent| parent.get()) {
+        Ok(device_entity)
+            if q_devices.get(device_entity).is_ok_and(|children| {
+                children
+                    .iter()
+                    .all(|child| *child == entity || !q_entities.contains(*child))
+            }) =>
+        {
+            debug!("Removing device {:?} without entities", device_entity);
+            commands.entity(device_entity).despawn_recursive();
+        }
+        _ => commands.entity(entity).despawn_recursive(),
+    }
+}
+
 #[derive(Bundle)]
 struct DiscoveryDefaultBundle {
     discovery_hash: MQTTDiscoveryHash,
@@ -346,27 +611,77 @@ pub struct DiscoveryData {
 }
 
 fn handle_discovery_message(payload: &[u8]) -> anyhow::Result<Map<String, Value>> {
-    let discovery_payload = match serde_json::from_slice::<Value>(payload) {
-        Err(_) => serde_json::Map::new(),
-        Ok(mut json_data) => {
-            let mut discovery_payload = json_data
-                .as_object_mut()
-                .ok_or_else(|| anyhow::anyhow!("Expected a JSON object"))?;
-            replace_all_abbreviations(&mut discovery_payload)?;
-            if !valid_origin_info(&discovery_payload) {
-                return Err(anyhow::anyhow!("Invalid origin info"));
-            }
+    let mut json_data = serde_json::from_slice::<Value>(payload)?;
+    let discovery_payload = json_data
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("Expected a JSON object"))?;
+    replace_all_abbreviations(discovery_payload)?;
+    if !valid_origin_info(discovery_payload) {
+        return Err(anyhow::anyhow!("Invalid origin info"));
+    }
 
-            if discovery_payload.contains_key(TOPIC_BASE) {
-                replace_topic_base(&mut discovery_payload);
-            }
+    if discovery_payload.contains_key(TOPIC_BASE) {
+        replace_topic_base(discovery_payload);
+    }
 
-            discovery_payload
-        }
-        .to_owned(),
+    Ok(discovery_payload.to_owned())
+}
+
+/// Split a device discovery payload into the `(component, component_id, config)` of each of its
+/// components, sharing the device, origin and shared topics with every component.
+fn handle_device_discovery_message(
+    payload: &[u8],
+) -> anyhow::Result<Vec<(String, String, Map<String, Value>)>> {
+    let mut json_data = serde_json::from_slice::<Value>(payload)?;
+    let device_payload = json_data
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("Expected a JSON object"))?;
+    replace_all_abbreviations(device_payload)?;
+    if !valid_origin_info(device_payload) {
+        return Err(anyhow::anyhow!("Invalid origin info"));
+    }
+    if !device_payload.contains_key(CONF_DEVICE) {
+        return Err(anyhow::anyhow!("Device discovery requires device info"));
+    }
+    let Some(Value::Object(components)) = device_payload.remove(CONF_COMPONENTS) else {
+        return Err(anyhow::anyhow!("Expected a {} map", CONF_COMPONENTS));
     };
 
-    Ok(discovery_payload)
+    let mut discovered = vec![];
+    for (component_id, config) in components {
+        let Value::Object(mut config) = config else {
+            warn!("Ignoring invalid config of component {}", component_id);
+            continue;
+        };
+        replace_all_abbreviations(&mut config)?;
+        for key in DEVICE_SHARED_OPTIONS {
+            if let Some(value) = device_payload.get(*key) {
+                config
+                    .entry(key.to_string())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        let component = match config.remove(CONF_PLATFORM) {
+            Some(Value::String(component))
+                if SUPPORTED_COMPONENTS.contains(&component.as_str()) =>
+            {
+                component
+            }
+            platform => {
+                warn!(
+                    "Ignoring component {} with unsupported platform {:?}",
+                    component_id, platform
+                );
+                continue;
+            }
+        };
+        if config.contains_key(TOPIC_BASE) {
+            replace_topic_base(&mut config);
+        }
+        discovered.push((component, component_id, config));
+    }
+
+    Ok(discovered)
 }
 
 /// Spawn or Update MQTT components from discovery payload
@@ -375,7 +690,9 @@ fn spawn_or_update_components(cmds: &mut EntityCommands, discovery_payload: &MQT
         serde_json::from_value::<MQTTDiscoveryComponents>(discovery_payload.payload.clone())
     {
         cmds.insert(discovery_payload.clone());
-        cmds.insert(components.state_subscription);
+        if let Some(state_subscription) = components.state_subscription {
+            cmds.insert(state_subscription);
+        }
         if let Some(availability_config) = components.availability_config {
             let availability = MQTTAvailability::from_config(availability_config);
 
@@ -398,20 +715,25 @@ fn spawn_or_update_components(cmds: &mut EntityCommands, discovery_payload: &MQT
         if let Some(entity_category) = components.entity_category {
             cmds.insert(entity_category);
         }
+
+        if let Some(json_attributes) = components.json_attributes {
+            cmds.insert(json_attributes);
+        }
     }
 }
 
 #[derive(Deserialize, Debug, Clone)]
 struct MQTTDiscoveryComponents {
     #[serde(flatten)]
-    state_subscription: MQTTStateSubscription,
+    state_subscription: Option<MQTTStateSubscription>,
     #[serde(flatten)]
     availability_config: Option<MQTTAvailabilityConfiguration>,
-    #[serde(flatten)]
     entity_category: Option<EntityCategory>,
     device: Option<DeviceSpec>,
     #[serde(flatten)]
     state_attributes: Option<StateAttributes>,
+    #[serde(flatten)]
+    json_attributes: Option<MQTTJsonAttributesConfiguration>,
 }
 
 // Replace all abbreviations in the payload
@@ -492,8 +814,29 @@ struct MqttOriginInfo {
 
 const TOPIC_BASE: &str = "~";
 const CONF_AVAILABILITY: &str = "availability";
+const CONF_COMPONENTS: &str = "components";
 const CONF_TOPIC: &str = "topic";
 
+/// Config topic component of a device discovery payload, `<prefix>/device/<object_id>/config`.
+const DEVICE_DISCOVERY: &str = "device";
+
+/// Options of a device discovery payload that apply to each of its components.
+const DEVICE_SHARED_OPTIONS: &[&str] = &[
+    CONF_AVAILABILITY,
+    "availability_mode",
+    "availability_template",
+    "availability_topic",
+    "command_topic",
+    CONF_DEVICE,
+    "encoding",
+    "origin",
+    "payload_available",
+    "payload_not_available",
+    "qos",
+    "state_topic",
+    TOPIC_BASE,
+];
+
 /// Replace topic base in MQTT discovery data.
 fn replace_topic_base(discovery_payload: &mut Map<String, Value>) {
     if let Some(base) = discovery_payload.remove(TOPIC_BASE) {
@@ -572,3 +915,155 @@ fn test_availability() {
 
     let availability = serde_json::from_value::<MQTTAvailability>(json.clone()).unwrap();
 }
+
+#[test]
+fn test_remove_entity_from_discovery() {
+    use bevy_hierarchy::BuildWorldChildren;
+
+    let mut world = World::new();
+    world.observe(remove_entity_from_discovery);
+    let hash = |discovery_id: &str| MQTTDiscoveryHash {
+        component: "sensor".to_string(),
+        discovery_id: discovery_id.to_string(),
+    };
+
+    let platform = world.spawn(SkepMqttPlatform::default()).id();
+    let device = world.spawn(Device::default()).set_parent(platform).id();
+    let battery = world.spawn(hash("battery")).set_parent(device).id();
+    let battery_topic = world
+        .spawn(SubscribeTopic::new("zigbee2mqtt/hallway_motion", 0))
+        .set_parent(battery)
+        .id();
+    let voltage = world.spawn(hash("voltage")).set_parent(device).id();
+    world.flush();
+
+    world.trigger_targets(MQTTDiscoveryRemove(hash("battery")), platform);
+    world.flush();
+    assert!(world.get_entity(battery).is_none());
+    assert!(world.get_entity(battery_topic).is_none());
+    assert!(world.get_entity(device).is_some());
+
+    world.trigger_targets(MQTTDiscoveryRemove(hash("voltage")), platform);
+    world.flush();
+    assert!(world.get_entity(voltage).is_none());
+    assert!(world.get_entity(device).is_none());
+}
+
+#[test]
+fn test_discovery_message() {
+    let config = handle_discovery_message(br#"{"~": "hallway", "stat_t": "~/state"}"#).unwrap();
+    assert_eq!(config["state_topic"], json!("hallway/state"));
+
+    assert!(handle_discovery_message(b"{not json").is_err());
+    assert!(handle_discovery_message(b"[]").is_err());
+}
+
+#[test]
+fn test_device_discovery() {
+    let payload = json!({
+        "dev": {"ids": "0AFFD2", "name": "Living room climate"},
+        "o": {"name": "bla2mqtt", "sw": "2.1"},
+        "~": "livingroom/climate",
+        "stat_t": "~/state",
+        "qos": 1,
+        "cmps": {
+            "temperature": {
+                "p": "sensor",
+                "dev_cla": "temperature",
+                "unit_of_meas": "°C",
+                "val_tpl": "{{ value_json.temperature }}",
+                "uniq_id": "0AFFD2_temperature"
+            },
+            "mode": {
+                "p": "select",
+                "ops": ["auto", "off"],
+                "cmd_t": "~/mode/set",
+                "stat_t": "~/mode"
+            },
+            "bogus": {"p": "toaster"}
+        }
+    });
+
+    let mut components = handle_device_discovery_message(payload.to_string().as_bytes()).unwrap();
+    components.sort_by(|a, b| a.1.cmp(&b.1));
+    assert_eq!(components.len(), 2);
+
+    let (component, component_id, config) = &components[0];
+    assert_eq!(
+        (component.as_str(), component_id.as_str()),
+        ("select", "mode")
+    );
+    assert_eq!(config["state_topic"], json!("livingroom/climate/mode"));
+    assert_eq!(
+        config["command_topic"],
+        json!("livingroom/climate/mode/set")
+    );
+    assert_eq!(config["qos"], json!(1));
+
+    let (component, component_id, config) = &components[1];
+    assert_eq!(
+        (component.as_str(), component_id.as_str()),
+        ("sensor", "temperature")
+    );
+    assert_eq!(config["state_topic"], json!("livingroom/climate/state"));
+    assert_eq!(config["device"]["name"], json!("Living room climate"));
+    assert!(!config.contains_key("platform"));
+
+    assert!(handle_device_discovery_message(br#"{"cmps": {}}"#).is_err());
+}
+
+#[test]
+fn test_pending_discovery() {
+    use bevy_ecs::system::RunSystemOnce;
+
+    let mut world = World::new();
+    world.observe(setup_new_entity_from_discovery);
+    world.observe(update_entity_from_discovery);
+    world.observe(finish_pending_discovery);
+    let platform = world.spawn(SkepMqttPlatform::default()).id();
+
+    let hash = MQTTDiscoveryHash {
+        component: "sensor".to_string(),
+        discovery_id: "hallway_temperature".to_string(),
+    };
+    let payload = |name: &str| MQTTDiscoveryPayload {
+        topic: "sensor/hallway_temperature/config".to_string(),
+        hash: hash.clone(),
+        payload: json!({
+            "name": name,
+            "state_topic": "hallway/temperature",
+            "device": {"identifiers": ["hallway"], "name": "Hallway"}
+        }),
+        platform: "mqtt".to_string(),
+    };
+    // The identical republished config is skipped, only the newest queued one is applied
+    let payloads = vec![
+        payload("first"),
+        payload("second"),
+        payload("second"),
+        payload("latest"),
+    ];
+    world.run_system_once(
+        move |mut commands: Commands, mut q_platform: Query<&mut SkepMqttPlatform>| {
+            let mut mqtt_platform = q_platform.get_mut(platform).unwrap();
+            for discovery_payload in payloads.clone() {
+                discover(
+                    &mut mqtt_platform,
+                    &mut commands,
+                    platform,
+                    discovery_payload,
+                );
+            }
+        },
+    );
+    world.flush();
+
+    let mut q_entities = world.query::<(&MQTTDiscoveryHash, &MQTTDiscoveryPayload)>();
+    let (entity_hash, entity_payload) = q_entities.single(&world);
+    assert_eq!(entity_hash, &hash);
+    assert_eq!(entity_payload.payload["name"], json!("latest"));
+
+    let mqtt_platform = world.get::<SkepMqttPlatform>(platform).unwrap();
+    assert!(mqtt_platform.discovery_pending_discovered.is_empty());
+    assert!(mqtt_platform.discovery_already_discovered.contains(&hash));
+}
@@ -4,7 +4,7 @@ use crate::{
         CONF_ENABLED_BY_DEFAULT, CONF_OBJECT_ID, CONF_PAYLOAD_AVAILABLE,
         CONF_PAYLOAD_NOT_AVAILABLE, CONF_TOPIC,
     },
-    discovery::MQTTDiscoveryPayload,
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
     subscription::MQTTStateSubscription,
     DiscoveryInfoType,
 };
@@ -14,30 +14,42 @@ use bevy_ecs::{
     component::Component,
     entity::Entity,
     observer::Trigger,
-    prelude::{Commands, In, Query, ResMut, System},
+    prelude::{Commands, In, Query, ResMut, System, Without},
+    system::SystemParam,
+    world::{CommandQueue, World},
+};
+use bevy_hierarchy::{HierarchyQueryExt, Parent};
+use bevy_log::{debug, warn};
+use bevy_mqtt::{
+    rumqttc::{self, QoS},
+    MqttClient, TopicMessage,
 };
-use bevy_log::debug;
-use bevy_mqtt::TopicMessage;
 use bevy_reflect::{Reflect, ReflectDeserialize, ReflectSerialize};
 use bevy_utils::HashMap;
+use lazy_static::lazy_static;
 use minijinja::{context, Environment, Template};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use skep_core::{
     config_entry::ConfigEntry,
     constants::{
         EntityCategory, CONF_DEVICE, CONF_ENTITY_CATEGORY, CONF_ICON, CONF_NAME, CONF_UNIQUE_ID,
         CONF_VALUE_TEMPLATE,
     },
-    helper::entity::{SkepEntity, SkepEntityComponent},
+    helper::{
+        entity::{SkepEntity, SkepEntityComponent},
+        event::DelayedAction,
+    },
     states::State,
     typing::ConfigType,
     CallbackType, SkepResource,
 };
 use std::{
     cmp::PartialEq,
+    collections::HashSet,
     str::FromStr,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
 #[derive(Debug, Default)]
@@ -395,10 +407,112 @@ impl MQTTAvailability {
     }
 }
 
+lazy_static! {
+    /// Attributes a JSON attributes payload can never override, on any platform.
+    static ref MQTT_ATTRIBUTES_BLOCKED: HashSet<&'static str> = HashSet::from([
+        "assumed_state",
+        "available",
+        "device_class",
+        "entity_category",
+        "entity_picture",
+        "entity_registry_enabled_default",
+        "extra_state_attributes",
+        "force_update",
+        "friendly_name",
+        "icon",
+        "should_poll",
+        "state",
+        "supported_features",
+        "unique_id",
+        "unit_of_measurement",
+    ]);
+}
+
+/// Attributes a platform sets itself, so a JSON attributes payload can't override them. Inserted
+/// by the platform alongside its config.
+#[derive(Debug, Component, Clone, Copy, Deref)]
+pub(crate) struct MQTTAttributesBlocked(pub &'static HashSet<&'static str>);
+
+/// `json_attributes_topic` and `json_attributes_template`, shared by all platforms.
+#[derive(Debug, Serialize, Deserialize, Component, Default, Reflect, Clone, PartialEq)]
+pub struct MQTTJsonAttributesConfiguration {
+    pub json_attributes_topic: Option<String>,
+    pub json_attributes_template: Option<String>,
+}
+
+impl MQTTJsonAttributesConfiguration {
+    /// Render a JSON attributes payload into a dict, dropping the blocked attributes and the
+    /// `extra_blocked` ones of the platform.
+    pub fn parse_attributes(
+        &self,
+        extra_blocked: Option<&HashSet<&str>>,
+        payload: &[u8],
+    ) -> anyhow::Result<HashMap<String, Value>> {
+        let value = try_render_template(&self.json_attributes_template, payload)?;
+        let attributes = serde_json::from_str::<Map<String, Value>>(&value)?;
+
+        Ok(attributes
+            .into_iter()
+            .filter(|(key, _)| {
+                !MQTT_ATTRIBUTES_BLOCKED.contains(key.as_str())
+                    && !extra_blocked.is_some_and(|blocked| blocked.contains(key.as_str()))
+            })
+            .collect())
+    }
+}
+
+/// Replace the extra state attributes of an entity with the dict of its JSON attributes topic.
+pub(crate) fn handle_json_attributes(
+    topic_message: Trigger<TopicMessage>,
+    mut commands: Commands,
+    mut q_attributes: Query<(
+        &MQTTDiscoveryHash,
+        &MQTTJsonAttributesConfiguration,
+        Option<&MQTTAttributesBlocked>,
+        Option<&mut SkepEntityComponent>,
+    )>,
+) {
+    let entity = topic_message.entity();
+    let Ok((hash, config, opt_blocked, opt_skep_entity)) = q_attributes.get_mut(entity) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.json_attributes_topic.as_deref() != Some(topic) {
+        return;
+    }
+
+    let extra_blocked = opt_blocked.map(|blocked| blocked.0);
+    let attributes = match config.parse_attributes(extra_blocked, &topic_message.event().payload) {
+        Ok(attributes) => attributes,
+        Err(e) => {
+            warn!("Ignoring JSON attributes of {} on {}: {}", hash, topic, e);
+            return;
+        }
+    };
+    debug!("{} attributes: {:?}", hash, attributes);
+    match opt_skep_entity {
+        Some(mut skep_entity) => skep_entity.extra_state_attributes = attributes,
+        None => {
+            let mut skep_entity = SkepEntityComponent::default();
+            skep_entity.entity_id = Some(hash.entity_id());
+            skep_entity.extra_state_attributes = attributes;
+            commands.entity(entity).insert(skep_entity);
+        }
+    }
+}
+
+/// Marker for entities whose platform maps state topic payloads itself, so the rendered value is
+/// not written to [`State`] verbatim by [`handle_state_value`].
+#[derive(Debug, Component, Default, Clone, Copy)]
+pub struct MQTTPlatformState;
+
 pub(crate) fn handle_state_value(
     topic_message: Trigger<TopicMessage>,
     mut commands: Commands,
-    mut q_state_sub: Query<(Entity, &MQTTStateSubscription, &Name, Option<&mut State>)>,
+    mut q_state_sub: Query<
+        (Entity, &MQTTStateSubscription, &Name, Option<&mut State>),
+        Without<MQTTPlatformState>,
+    >,
 ) {
     if let Ok((entity, state_sub, name, mut opt_state)) =
         q_state_sub.get_mut(topic_message.entity())
@@ -442,6 +556,90 @@ pub(crate) fn try_render_template(
     }
 }
 
+/// Render a `command_template` with `value`, falling back to the plain value when no template is
+/// configured.
+pub(crate) fn try_render_command_template(
+    command_template: &Option<String>,
+    value: &Value,
+) -> anyhow::Result<String> {
+    if let Some(command_template) = command_template {
+        let mut env = Environment::new();
+        env.add_template("command", command_template)?;
+        let template = env.get_template("command")?;
+
+        Ok(template.render(context! { value => value })?)
+    } else {
+        Ok(match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+    }
+}
+
+/// Render a command template with a set of named variables, e.g. `red`, `green` and `blue`.
+pub(crate) fn try_render_command_variables(
+    command_template: &str,
+    variables: &Map<String, Value>,
+) -> anyhow::Result<String> {
+    let mut env = Environment::new();
+    env.add_template("command", command_template)?;
+    let template = env.get_template("command")?;
+
+    Ok(template.render(variables)?)
+}
+
+/// Set the [`State`] of `entity` after `secs` seconds, e.g. when it expires. `None` when `secs`
+/// is negative or not finite.
+pub(crate) fn delayed_state(
+    entity: Entity,
+    secs: f32,
+    new_state: &'static str,
+) -> Option<DelayedAction> {
+    if let Err(e) = Duration::try_from_secs_f32(secs) {
+        warn!(
+            "Ignoring delayed state {} after {}s: {}",
+            new_state, secs, e
+        );
+        return None;
+    }
+    let mut action = CommandQueue::default();
+    action.push(move |world: &mut World| {
+        if let Some(mut state) = world.get_mut::<State>(entity) {
+            state.update(new_state);
+        }
+    });
+    Some(DelayedAction::new(secs, action))
+}
+
+/// Publishes commands for an MQTT entity through the client of the platform it belongs to.
+#[derive(SystemParam)]
+pub(crate) struct MqttPublisher<'w, 's> {
+    q_parent: Query<'w, 's, &'static Parent>,
+    q_client: Query<'w, 's, &'static MqttClient>,
+}
+
+impl<'w, 's> MqttPublisher<'w, 's> {
+    pub fn publish(
+        &self,
+        entity: Entity,
+        topic: &str,
+        qos: Option<u8>,
+        retain: Option<bool>,
+        payload: impl Into<Vec<u8>>,
+    ) -> anyhow::Result<()> {
+        let client = self
+            .q_parent
+            .iter_ancestors(entity)
+            .find_map(|ancestor| self.q_client.get(ancestor).ok())
+            .ok_or_else(|| anyhow::anyhow!("MQTT client not found for {:?}", entity))?;
+        let qos = rumqttc::qos(qos.unwrap_or(0)).unwrap_or(QoS::AtMostOnce);
+        client.publish(topic, qos, retain.unwrap_or(false), payload)?;
+        debug!("publish {} to {:?}", topic, entity);
+
+        Ok(())
+    }
+}
+
 pub(crate) fn handle_available_value(
     topic_message: Trigger<TopicMessage>,
     mut q_avail: Query<(&mut MQTTAvailability, &Name)>,
@@ -517,3 +715,27 @@ fn test_template() {
     let str = template.render(context! { value => "no error" }).unwrap();
     println!("str: {:?}", str);
 }
+
+#[test]
+fn test_json_attributes() {
+    let config: MQTTJsonAttributesConfiguration =
+        serde_json::from_str(r#"{"json_attributes_topic": "zigbee2mqtt/hallway_motion"}"#).unwrap();
+    let payload = br#"{"linkquality": 96, "battery": 87, "voltage": 2975, "icon": "mdi:run", "state_class": "measurement"}"#;
+
+    let sensor_blocked = HashSet::from(["last_reset", "state_class"]);
+    let attributes = config
+        .parse_attributes(Some(&sensor_blocked), payload)
+        .unwrap();
+    assert_eq!(attributes.len(), 3);
+    assert_eq!(attributes.get("linkquality"), Some(&Value::from(96)));
+    assert_eq!(attributes.get("voltage"), Some(&Value::from(2975)));
+
+    let attributes = config.parse_attributes(None, payload).unwrap();
+    assert_eq!(
+        attributes.get("state_class"),
+        Some(&Value::from("measurement"))
+    );
+    assert!(!attributes.contains_key("icon"));
+
+    assert!(config.parse_attributes(None, b"87").is_err());
+}
@@ -0,0 +1,166 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_template, MQTTPlatformState},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use skep_core::{constants::STATE_UNKNOWN, states::State};
+
+pub struct MqttEventPlugin;
+
+impl Plugin for MqttEventPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_event_state);
+    }
+}
+
+const DOMAIN: &str = "event";
+
+const ATTR_EVENT_TYPE: &str = "event_type";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    mut q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&mut EventAttributes>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state, opt_attributes) in q_discovery.iter_mut() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttEventConfiguration>(payload.payload.clone())
+        {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid event config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        match opt_attributes {
+            Some(mut attributes) => attributes.event_types = config.event_types.clone(),
+            None => {
+                cmds.insert(EventAttributes {
+                    event_types: config.event_types.clone(),
+                    ..Default::default()
+                });
+            }
+        }
+        cmds.insert((config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum EventDeviceClass {
+    Button,
+    Doorbell,
+    Motion,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttEventConfiguration {
+    /// Receives a JSON object with an `event_type` and optional extra attributes.
+    pub state_topic: String,
+    pub value_template: Option<String>,
+    /// The event types the entity can fire
+    pub event_types: Vec<String>,
+    pub device_class: Option<EventDeviceClass>,
+    pub qos: Option<u8>,
+}
+
+impl MqttEventConfiguration {
+    /// Parse a rendered event payload into the event type and its extra attributes.
+    pub fn parse_event(&self, value: &str) -> anyhow::Result<(String, Map<String, Value>)> {
+        let mut object = serde_json::from_str::<Map<String, Value>>(value)?;
+        let event_type = match object.remove(ATTR_EVENT_TYPE) {
+            Some(Value::String(event_type)) => event_type,
+            _ => anyhow::bail!("missing {}", ATTR_EVENT_TYPE),
+        };
+        if !self.event_types.contains(&event_type) {
+            anyhow::bail!("unsupported event type {}", event_type);
+        }
+        Ok((event_type, object))
+    }
+}
+
+/// The last fired event, the [`State`] holds its event type.
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct EventAttributes {
+    pub event_types: Vec<String>,
+    pub event_type: Option<String>,
+    /// Extra attributes sent with the last event
+    pub attributes: Map<String, Value>,
+}
+
+fn handle_event_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_event: Query<(&MqttEventConfiguration, &mut State, &mut EventAttributes)>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_event.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic != topic {
+        return;
+    }
+    let value = try_render_template(&config.value_template, &topic_message.event().payload)
+        .unwrap_or_default();
+    match config.parse_event(&value) {
+        Ok((event_type, extra)) => {
+            state.update(&event_type);
+            attributes.event_type = Some(event_type);
+            attributes.attributes = extra;
+        }
+        Err(e) => warn!("Ignoring event payload {:?} on {}: {}", value, topic, e),
+    }
+}
+
+#[test]
+fn test_event_configuration() {
+    let json = r#"
+    {
+        "state_topic": "doorbell/event",
+        "event_types": ["press", "hold"],
+        "device_class": "doorbell"
+    }
+    "#;
+    let config: MqttEventConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.device_class, Some(EventDeviceClass::Doorbell));
+
+    let (event_type, extra) = config
+        .parse_event(r#"{"event_type": "hold", "duration": 2}"#)
+        .unwrap();
+    assert_eq!(event_type, "hold");
+    assert_eq!(extra.get("duration"), Some(&Value::from(2)));
+    assert!(config.parse_event(r#"{"event_type": "release"}"#).is_err());
+    assert!(config.parse_event(r#"{"duration": 2}"#).is_err());
+    assert!(config.parse_event("press").is_err());
+}
@@ -0,0 +1,502 @@
+use crate::{
+    constants::{DEFAULT_PAYLOAD_OFF, DEFAULT_PAYLOAD_ON, PAYLOAD_NONE},
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{
+        try_render_command_template, try_render_template, MQTTAttributesBlocked, MQTTPlatformState,
+        MqttPublisher,
+    },
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::Children;
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use skep_core::{
+    constants::{STATE_OFF, STATE_ON, STATE_UNKNOWN},
+    states::State,
+};
+use std::{collections::HashSet, str::FromStr};
+use strum_macros::{Display, EnumString};
+
+lazy_static! {
+    static ref MQTT_FAN_ATTRIBUTES_BLOCKED: HashSet<&'static str> = HashSet::from([
+        "direction",
+        "oscillating",
+        "percentage",
+        "percentage_step",
+        "preset_mode",
+        "preset_modes",
+    ]);
+}
+
+pub struct MqttFanPlugin;
+
+impl Plugin for MqttFanPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_fan_state)
+        .observe(on_fan_command);
+    }
+}
+
+const DOMAIN: &str = "fan";
+
+const DEFAULT_SPEED_RANGE_MIN: u32 = 1;
+const DEFAULT_SPEED_RANGE_MAX: u32 = 100;
+const DEFAULT_PAYLOAD_OSCILLATION_ON: &str = "oscillate_on";
+const DEFAULT_PAYLOAD_OSCILLATION_OFF: &str = "oscillate_off";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    mut q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&mut FanAttributes>,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_state, opt_attributes, opt_children) in q_discovery.iter_mut() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttFanConfiguration>(payload.payload.clone()) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid fan config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            [
+                &config.percentage_state_topic,
+                &config.preset_mode_state_topic,
+                &config.oscillation_state_topic,
+                &config.direction_state_topic,
+            ]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .filter(|topic| Some(*topic) != config.state_topic.as_deref()),
+            config.qos.unwrap_or(0),
+        );
+        let mut cmds = commands.entity(entity);
+        match opt_attributes {
+            Some(mut attributes) => attributes.update_from_config(&config),
+            None => {
+                let mut attributes = FanAttributes::default();
+                attributes.update_from_config(&config);
+                cmds.insert(attributes);
+            }
+        }
+        cmds.insert((
+            config,
+            MQTTPlatformState,
+            MQTTAttributesBlocked(&MQTT_FAN_ATTRIBUTES_BLOCKED),
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[strum(ascii_case_insensitive)]
+pub enum FanDirection {
+    Forward,
+    Reverse,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttFanConfiguration {
+    pub command_topic: Option<String>,
+    pub command_template: Option<String>,
+    pub state_topic: Option<String>,
+    pub state_value_template: Option<String>,
+    pub payload_on: Option<String>,
+    pub payload_off: Option<String>,
+    pub percentage_command_topic: Option<String>,
+    pub percentage_command_template: Option<String>,
+    pub percentage_state_topic: Option<String>,
+    pub percentage_value_template: Option<String>,
+    /// The minimum of numeric output range (`off` not included, so `speed_range_min` - 1
+    /// represents 0 %).
+    pub speed_range_min: Option<u32>,
+    /// The maximum of numeric output range, representing 100 %.
+    pub speed_range_max: Option<u32>,
+    pub payload_reset_percentage: Option<String>,
+    pub preset_mode_command_topic: Option<String>,
+    pub preset_mode_command_template: Option<String>,
+    pub preset_mode_state_topic: Option<String>,
+    pub preset_mode_value_template: Option<String>,
+    #[serde(default)]
+    pub preset_modes: Vec<String>,
+    pub payload_reset_preset_mode: Option<String>,
+    pub oscillation_command_topic: Option<String>,
+    pub oscillation_command_template: Option<String>,
+    pub oscillation_state_topic: Option<String>,
+    pub oscillation_value_template: Option<String>,
+    pub payload_oscillation_on: Option<String>,
+    pub payload_oscillation_off: Option<String>,
+    pub direction_command_topic: Option<String>,
+    pub direction_command_template: Option<String>,
+    pub direction_state_topic: Option<String>,
+    pub direction_value_template: Option<String>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttFanConfiguration {
+    pub fn payload_on(&self) -> &str {
+        self.payload_on.as_deref().unwrap_or(DEFAULT_PAYLOAD_ON)
+    }
+
+    pub fn payload_off(&self) -> &str {
+        self.payload_off.as_deref().unwrap_or(DEFAULT_PAYLOAD_OFF)
+    }
+
+    pub fn payload_oscillation_on(&self) -> &str {
+        self.payload_oscillation_on
+            .as_deref()
+            .unwrap_or(DEFAULT_PAYLOAD_OSCILLATION_ON)
+    }
+
+    pub fn payload_oscillation_off(&self) -> &str {
+        self.payload_oscillation_off
+            .as_deref()
+            .unwrap_or(DEFAULT_PAYLOAD_OSCILLATION_OFF)
+    }
+
+    fn speed_range(&self) -> (u32, u32) {
+        (
+            self.speed_range_min.unwrap_or(DEFAULT_SPEED_RANGE_MIN),
+            self.speed_range_max.unwrap_or(DEFAULT_SPEED_RANGE_MAX),
+        )
+    }
+
+    /// Number of speeds the fan supports.
+    pub fn speed_count(&self) -> u32 {
+        let (min, max) = self.speed_range();
+        max.saturating_sub(min) + 1
+    }
+
+    /// Convert a device speed in `speed_range_min..=speed_range_max` to a percentage.
+    pub fn speed_to_percentage(&self, speed: f64) -> u8 {
+        let (min, _) = self.speed_range();
+        let offset = min as f64 - 1.0;
+        ((speed - offset) * 100.0 / self.speed_count() as f64)
+            .floor()
+            .clamp(0.0, 100.0) as u8
+    }
+
+    /// Convert a percentage to a device speed in `speed_range_min..=speed_range_max`.
+    pub fn percentage_to_speed(&self, percentage: u8) -> u32 {
+        let (min, _) = self.speed_range();
+        let offset = min as f64 - 1.0;
+        (offset + self.speed_count() as f64 * percentage as f64 / 100.0).ceil() as u32
+    }
+
+    fn optimistic_for(&self, state_topic: &Option<String>) -> bool {
+        self.optimistic.unwrap_or(false) || state_topic.is_none()
+    }
+}
+
+/// Current fan values next to the on/off [`State`].
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct FanAttributes {
+    pub percentage: Option<u8>,
+    pub preset_mode: Option<String>,
+    pub oscillating: Option<bool>,
+    pub direction: Option<FanDirection>,
+    pub preset_modes: Vec<String>,
+    pub speed_count: u32,
+}
+
+impl FanAttributes {
+    fn update_from_config(&mut self, config: &MqttFanConfiguration) {
+        self.preset_modes = config.preset_modes.clone();
+        self.speed_count = config.speed_count();
+    }
+}
+
+/// Commands accepted by an MQTT fan entity, triggered with the fan as target.
+#[derive(Debug, Event, Clone, PartialEq)]
+pub enum FanCommand {
+    TurnOn {
+        percentage: Option<u8>,
+        preset_mode: Option<String>,
+    },
+    TurnOff,
+    Toggle,
+    SetPercentage(u8),
+    SetPresetMode(String),
+    Oscillate(bool),
+    SetDirection(FanDirection),
+}
+
+fn handle_fan_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_fan: Query<(&MqttFanConfiguration, &mut State, &mut FanAttributes)>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_fan.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    let payload = &topic_message.event().payload[..];
+    let is_topic = |t: &Option<String>| t.as_deref() == Some(topic);
+
+    if is_topic(&config.state_topic) {
+        let value = try_render_template(&config.state_value_template, payload).unwrap_or_default();
+        if value == config.payload_on() {
+            state.update(STATE_ON);
+        } else if value == config.payload_off() {
+            state.update(STATE_OFF);
+        } else if value == PAYLOAD_NONE {
+            state.update(STATE_UNKNOWN);
+        } else if !value.is_empty() {
+            warn!("Ignoring fan state payload {:?} on {}", value, topic);
+        }
+    }
+
+    if is_topic(&config.percentage_state_topic) {
+        let value =
+            try_render_template(&config.percentage_value_template, payload).unwrap_or_default();
+        let reset = config
+            .payload_reset_percentage
+            .as_deref()
+            .unwrap_or(PAYLOAD_NONE);
+        if value == reset {
+            attributes.percentage = None;
+        } else if let Ok(speed) = value.trim().parse::<f64>() {
+            let (min, max) = config.speed_range();
+            if speed == 0.0 {
+                attributes.percentage = Some(0);
+            } else if speed < min as f64 || speed > max as f64 {
+                warn!(
+                    "Fan speed {} on {} is out of range {}-{}",
+                    speed, topic, min, max
+                );
+            } else {
+                attributes.percentage = Some(config.speed_to_percentage(speed));
+            }
+        } else if !value.is_empty() {
+            warn!("Ignoring fan percentage payload {:?} on {}", value, topic);
+        }
+    }
+
+    if is_topic(&config.preset_mode_state_topic) {
+        let value =
+            try_render_template(&config.preset_mode_value_template, payload).unwrap_or_default();
+        let reset = config
+            .payload_reset_preset_mode
+            .as_deref()
+            .unwrap_or(PAYLOAD_NONE);
+        if value == reset {
+            attributes.preset_mode = None;
+        } else if config.preset_modes.contains(&value) {
+            attributes.preset_mode = Some(value);
+        } else if !value.is_empty() {
+            warn!("Ignoring invalid fan preset mode {:?} on {}", value, topic);
+        }
+    }
+
+    if is_topic(&config.oscillation_state_topic) {
+        let value =
+            try_render_template(&config.oscillation_value_template, payload).unwrap_or_default();
+        if value == config.payload_oscillation_on() {
+            attributes.oscillating = Some(true);
+        } else if value == config.payload_oscillation_off() {
+            attributes.oscillating = Some(false);
+        } else if !value.is_empty() {
+            warn!("Ignoring fan oscillation payload {:?} on {}", value, topic);
+        }
+    }
+
+    if is_topic(&config.direction_state_topic) {
+        let value =
+            try_render_template(&config.direction_value_template, payload).unwrap_or_default();
+        match FanDirection::from_str(value.trim()) {
+            Ok(direction) => attributes.direction = Some(direction),
+            Err(_) if !value.is_empty() => {
+                warn!("Ignoring fan direction payload {:?} on {}", value, topic)
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+fn on_fan_command(
+    trigger: Trigger<FanCommand>,
+    publisher: MqttPublisher,
+    mut q_fan: Query<(&MqttFanConfiguration, &mut State, &mut FanAttributes)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state, mut attributes)) = q_fan.get_mut(entity) else {
+        return;
+    };
+
+    let publish = |topic: &Option<String>, template: &Option<String>, value: Value| -> bool {
+        let Some(topic) = topic else {
+            return false;
+        };
+        let payload = match try_render_command_template(template, &value) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to render fan command for {}: {}", topic, e);
+                return false;
+            }
+        };
+        match publisher.publish(entity, topic, config.qos, config.retain, payload) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Failed to publish fan command: {}", e);
+                false
+            }
+        }
+    };
+
+    let mut set_percentage = None;
+    let mut set_preset_mode = None;
+    let turn_on = match trigger.event().clone() {
+        FanCommand::TurnOn {
+            percentage,
+            preset_mode,
+        } => {
+            set_percentage = percentage;
+            set_preset_mode = preset_mode;
+            Some(true)
+        }
+        FanCommand::TurnOff => Some(false),
+        FanCommand::Toggle => Some(state.state != STATE_ON),
+        FanCommand::SetPercentage(percentage) => {
+            set_percentage = Some(percentage);
+            None
+        }
+        FanCommand::SetPresetMode(preset_mode) => {
+            set_preset_mode = Some(preset_mode);
+            None
+        }
+        FanCommand::Oscillate(oscillating) => {
+            let payload = if oscillating {
+                config.payload_oscillation_on()
+            } else {
+                config.payload_oscillation_off()
+            };
+            if publish(
+                &config.oscillation_command_topic,
+                &config.oscillation_command_template,
+                json!(payload),
+            ) && config.optimistic_for(&config.oscillation_state_topic)
+            {
+                attributes.oscillating = Some(oscillating);
+            }
+            None
+        }
+        FanCommand::SetDirection(direction) => {
+            if publish(
+                &config.direction_command_topic,
+                &config.direction_command_template,
+                json!(direction),
+            ) && config.optimistic_for(&config.direction_state_topic)
+            {
+                attributes.direction = Some(direction);
+            }
+            None
+        }
+    };
+
+    if let Some(turn_on) = turn_on {
+        let (payload, new_state) = if turn_on {
+            (config.payload_on(), STATE_ON)
+        } else {
+            (config.payload_off(), STATE_OFF)
+        };
+        if publish(
+            &config.command_topic,
+            &config.command_template,
+            json!(payload),
+        ) && config.optimistic_for(&config.state_topic)
+        {
+            state.update(new_state);
+        }
+    }
+
+    if let Some(percentage) = set_percentage {
+        let percentage = percentage.min(100);
+        if publish(
+            &config.percentage_command_topic,
+            &config.percentage_command_template,
+            json!(config.percentage_to_speed(percentage)),
+        ) && config.optimistic_for(&config.percentage_state_topic)
+        {
+            attributes.percentage = Some(percentage);
+        }
+    }
+
+    if let Some(preset_mode) = set_preset_mode {
+        if !config.preset_modes.contains(&preset_mode) {
+            warn!("Unsupported fan preset mode: {}", preset_mode);
+            return;
+        }
+        if publish(
+            &config.preset_mode_command_topic,
+            &config.preset_mode_command_template,
+            json!(preset_mode),
+        ) && config.optimistic_for(&config.preset_mode_state_topic)
+        {
+            attributes.preset_mode = Some(preset_mode);
+        }
+    }
+}
+
+#[test]
+fn test_fan_speed_range() {
+    let json = r#"
+    {
+        "command_topic": "fan/set",
+        "percentage_command_topic": "fan/speed/set",
+        "speed_range_min": 1,
+        "speed_range_max": 3,
+        "preset_modes": ["auto", "smart"]
+    }
+    "#;
+    let config: MqttFanConfiguration = serde_json::from_str(json).unwrap();
+
+    assert_eq!(config.speed_count(), 3);
+    assert_eq!(config.speed_to_percentage(1.0), 33);
+    assert_eq!(config.speed_to_percentage(3.0), 100);
+    assert_eq!(config.percentage_to_speed(33), 1);
+    assert_eq!(config.percentage_to_speed(50), 2);
+    assert_eq!(config.percentage_to_speed(100), 3);
+    assert_eq!(config.percentage_to_speed(0), 0);
+    assert_eq!(config.payload_oscillation_on(), "oscillate_on");
+    assert!(config.optimistic_for(&config.state_topic));
+}
@@ -0,0 +1,433 @@
+use crate::{
+    constants::{DEFAULT_PAYLOAD_OFF, DEFAULT_PAYLOAD_ON, PAYLOAD_NONE},
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{
+        try_render_command_template, try_render_template, MQTTAttributesBlocked, MQTTPlatformState,
+        MqttPublisher,
+    },
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::Children;
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use skep_core::{
+    constants::{STATE_OFF, STATE_ON, STATE_UNKNOWN},
+    states::State,
+};
+use std::{collections::HashSet, str::FromStr};
+use strum_macros::{Display, EnumString};
+
+lazy_static! {
+    static ref MQTT_HUMIDIFIER_ATTRIBUTES_BLOCKED: HashSet<&'static str> = HashSet::from([
+        "action",
+        "available_modes",
+        "current_humidity",
+        "humidity",
+        "max_humidity",
+        "min_humidity",
+        "mode",
+    ]);
+}
+
+pub struct MqttHumidifierPlugin;
+
+impl Plugin for MqttHumidifierPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_humidifier_state)
+        .observe(on_humidifier_command);
+    }
+}
+
+const DOMAIN: &str = "humidifier";
+
+const DEFAULT_MIN_HUMIDITY: f32 = 0.0;
+const DEFAULT_MAX_HUMIDITY: f32 = 100.0;
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    mut q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&mut HumidifierAttributes>,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_state, opt_attributes, opt_children) in q_discovery.iter_mut() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttHumidifierConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid humidifier config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            [
+                &config.target_humidity_state_topic,
+                &config.mode_state_topic,
+                &config.current_humidity_topic,
+                &config.action_topic,
+            ]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .filter(|topic| Some(*topic) != config.state_topic.as_deref()),
+            config.qos.unwrap_or(0),
+        );
+        let mut cmds = commands.entity(entity);
+        match opt_attributes {
+            Some(mut attributes) => attributes.update_from_config(&config),
+            None => {
+                let mut attributes = HumidifierAttributes::default();
+                attributes.update_from_config(&config);
+                cmds.insert(attributes);
+            }
+        }
+        cmds.insert((
+            config,
+            MQTTPlatformState,
+            MQTTAttributesBlocked(&MQTT_HUMIDIFIER_ATTRIBUTES_BLOCKED),
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default, EnumString, Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum HumidifierDeviceClass {
+    #[default]
+    Humidifier,
+    Dehumidifier,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[strum(ascii_case_insensitive)]
+pub enum HumidifierAction {
+    Off,
+    Humidifying,
+    Drying,
+    Idle,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttHumidifierConfiguration {
+    pub command_topic: Option<String>,
+    pub command_template: Option<String>,
+    pub state_topic: Option<String>,
+    pub state_value_template: Option<String>,
+    pub payload_on: Option<String>,
+    pub payload_off: Option<String>,
+    pub device_class: Option<HumidifierDeviceClass>,
+    pub target_humidity_command_topic: Option<String>,
+    pub target_humidity_command_template: Option<String>,
+    pub target_humidity_state_topic: Option<String>,
+    pub target_humidity_state_template: Option<String>,
+    pub payload_reset_humidity: Option<String>,
+    pub min_humidity: Option<f32>,
+    pub max_humidity: Option<f32>,
+    pub mode_command_topic: Option<String>,
+    pub mode_command_template: Option<String>,
+    pub mode_state_topic: Option<String>,
+    pub mode_state_template: Option<String>,
+    #[serde(default)]
+    pub modes: Vec<String>,
+    pub payload_reset_mode: Option<String>,
+    pub current_humidity_topic: Option<String>,
+    pub current_humidity_template: Option<String>,
+    pub action_topic: Option<String>,
+    pub action_template: Option<String>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttHumidifierConfiguration {
+    pub fn payload_on(&self) -> &str {
+        self.payload_on.as_deref().unwrap_or(DEFAULT_PAYLOAD_ON)
+    }
+
+    pub fn payload_off(&self) -> &str {
+        self.payload_off.as_deref().unwrap_or(DEFAULT_PAYLOAD_OFF)
+    }
+
+    pub fn min_humidity(&self) -> f32 {
+        self.min_humidity.unwrap_or(DEFAULT_MIN_HUMIDITY)
+    }
+
+    pub fn max_humidity(&self) -> f32 {
+        self.max_humidity.unwrap_or(DEFAULT_MAX_HUMIDITY)
+    }
+
+    /// Render a humidity payload, `None` when it is the reset payload or not a number.
+    fn parse_humidity(&self, template: &Option<String>, payload: &[u8]) -> Option<f32> {
+        let value = try_render_template(template, payload).ok()?;
+        let reset = self
+            .payload_reset_humidity
+            .as_deref()
+            .unwrap_or(PAYLOAD_NONE);
+        if value == reset {
+            return None;
+        }
+        let humidity = value.trim().parse::<f32>().ok()?;
+        if humidity < self.min_humidity() || humidity > self.max_humidity() {
+            warn!(
+                "Humidity {} is out of range {}-{}",
+                humidity,
+                self.min_humidity(),
+                self.max_humidity()
+            );
+            return None;
+        }
+        Some(humidity)
+    }
+
+    fn optimistic_for(&self, state_topic: &Option<String>) -> bool {
+        self.optimistic.unwrap_or(false) || state_topic.is_none()
+    }
+}
+
+/// Current humidifier values next to the on/off [`State`].
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct HumidifierAttributes {
+    pub target_humidity: Option<f32>,
+    pub current_humidity: Option<f32>,
+    pub mode: Option<String>,
+    pub action: Option<HumidifierAction>,
+    pub available_modes: Vec<String>,
+    pub min_humidity: f32,
+    pub max_humidity: f32,
+    pub device_class: HumidifierDeviceClass,
+}
+
+impl HumidifierAttributes {
+    fn update_from_config(&mut self, config: &MqttHumidifierConfiguration) {
+        self.available_modes = config.modes.clone();
+        self.min_humidity = config.min_humidity();
+        self.max_humidity = config.max_humidity();
+        self.device_class = config.device_class.unwrap_or_default();
+    }
+}
+
+/// Commands accepted by an MQTT humidifier entity, triggered with the humidifier as target.
+#[derive(Debug, Event, Clone, PartialEq)]
+pub enum HumidifierCommand {
+    TurnOn,
+    TurnOff,
+    Toggle,
+    SetHumidity(f32),
+    SetMode(String),
+}
+
+fn handle_humidifier_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_humidifier: Query<(
+        &MqttHumidifierConfiguration,
+        &mut State,
+        &mut HumidifierAttributes,
+    )>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_humidifier.get_mut(topic_message.entity())
+    else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    let payload = &topic_message.event().payload[..];
+    let is_topic = |t: &Option<String>| t.as_deref() == Some(topic);
+
+    if is_topic(&config.state_topic) {
+        let value = try_render_template(&config.state_value_template, payload).unwrap_or_default();
+        if value == config.payload_on() {
+            state.update(STATE_ON);
+        } else if value == config.payload_off() {
+            state.update(STATE_OFF);
+        } else if value == PAYLOAD_NONE {
+            state.update(STATE_UNKNOWN);
+        } else if !value.is_empty() {
+            warn!("Ignoring humidifier state payload {:?} on {}", value, topic);
+        }
+    }
+
+    if is_topic(&config.target_humidity_state_topic) {
+        attributes.target_humidity =
+            config.parse_humidity(&config.target_humidity_state_template, payload);
+    }
+
+    if is_topic(&config.current_humidity_topic) {
+        attributes.current_humidity =
+            config.parse_humidity(&config.current_humidity_template, payload);
+    }
+
+    if is_topic(&config.mode_state_topic) {
+        let value = try_render_template(&config.mode_state_template, payload).unwrap_or_default();
+        let reset = config.payload_reset_mode.as_deref().unwrap_or(PAYLOAD_NONE);
+        if value == reset {
+            attributes.mode = None;
+        } else if config.modes.contains(&value) {
+            attributes.mode = Some(value);
+        } else if !value.is_empty() {
+            warn!("Ignoring invalid humidifier mode {:?} on {}", value, topic);
+        }
+    }
+
+    if is_topic(&config.action_topic) {
+        let value = try_render_template(&config.action_template, payload).unwrap_or_default();
+        if value == PAYLOAD_NONE {
+            attributes.action = None;
+        } else {
+            match HumidifierAction::from_str(value.trim()) {
+                Ok(action) => attributes.action = Some(action),
+                Err(_) => warn!(
+                    "Ignoring invalid humidifier action {:?} on {}",
+                    value, topic
+                ),
+            }
+        }
+    }
+}
+
+fn on_humidifier_command(
+    trigger: Trigger<HumidifierCommand>,
+    publisher: MqttPublisher,
+    mut q_humidifier: Query<(
+        &MqttHumidifierConfiguration,
+        &mut State,
+        &mut HumidifierAttributes,
+    )>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state, mut attributes)) = q_humidifier.get_mut(entity) else {
+        return;
+    };
+
+    let publish = |topic: &Option<String>, template: &Option<String>, value: Value| -> bool {
+        let Some(topic) = topic else {
+            return false;
+        };
+        let payload = match try_render_command_template(template, &value) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to render humidifier command for {}: {}", topic, e);
+                return false;
+            }
+        };
+        match publisher.publish(entity, topic, config.qos, config.retain, payload) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Failed to publish humidifier command: {}", e);
+                false
+            }
+        }
+    };
+
+    let turn_on = match trigger.event().clone() {
+        HumidifierCommand::TurnOn => true,
+        HumidifierCommand::TurnOff => false,
+        HumidifierCommand::Toggle => state.state != STATE_ON,
+        HumidifierCommand::SetHumidity(humidity) => {
+            let humidity = humidity.clamp(config.min_humidity(), config.max_humidity());
+            if publish(
+                &config.target_humidity_command_topic,
+                &config.target_humidity_command_template,
+                json!(humidity),
+            ) && config.optimistic_for(&config.target_humidity_state_topic)
+            {
+                attributes.target_humidity = Some(humidity);
+            }
+            return;
+        }
+        HumidifierCommand::SetMode(mode) => {
+            if !config.modes.contains(&mode) {
+                warn!("Unsupported humidifier mode: {}", mode);
+                return;
+            }
+            if publish(
+                &config.mode_command_topic,
+                &config.mode_command_template,
+                json!(mode),
+            ) && config.optimistic_for(&config.mode_state_topic)
+            {
+                attributes.mode = Some(mode);
+            }
+            return;
+        }
+    };
+
+    let (payload, new_state) = if turn_on {
+        (config.payload_on(), STATE_ON)
+    } else {
+        (config.payload_off(), STATE_OFF)
+    };
+    if publish(
+        &config.command_topic,
+        &config.command_template,
+        json!(payload),
+    ) && config.optimistic_for(&config.state_topic)
+    {
+        state.update(new_state);
+    }
+}
+
+#[test]
+fn test_humidifier_configuration() {
+    let json = r#"
+    {
+        "command_topic": "humidifier/set",
+        "target_humidity_command_topic": "humidifier/humidity/set",
+        "target_humidity_state_topic": "humidifier/humidity",
+        "device_class": "dehumidifier",
+        "min_humidity": 30,
+        "max_humidity": 80,
+        "modes": ["eco", "boost"],
+        "payload_reset_humidity": "\"\""
+    }
+    "#;
+    let config: MqttHumidifierConfiguration = serde_json::from_str(json).unwrap();
+
+    assert_eq!(
+        config.device_class,
+        Some(HumidifierDeviceClass::Dehumidifier)
+    );
+    assert_eq!(config.parse_humidity(&None, b"55"), Some(55.0));
+    assert_eq!(config.parse_humidity(&None, b"90"), None);
+    assert_eq!(config.parse_humidity(&None, b"\"\""), None);
+    assert!(config.optimistic_for(&config.state_topic));
+    assert!(!config.optimistic_for(&config.target_humidity_state_topic));
+}
@@ -0,0 +1,252 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_template, MQTTAttributesBlocked, MQTTPlatformState},
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{prelude::*, system::SystemParam};
+use bevy_hierarchy::Children;
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use skep_core::{constants::STATE_UNKNOWN, states::State};
+use std::collections::HashSet;
+
+lazy_static! {
+    static ref MQTT_IMAGE_ATTRIBUTES_BLOCKED: HashSet<&'static str> =
+        HashSet::from(["access_token", "entity_picture"]);
+}
+
+pub struct MqttImagePlugin;
+
+impl Plugin for MqttImagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_image_state);
+    }
+}
+
+const DOMAIN: &str = "image";
+
+pub(crate) const DEFAULT_CONTENT_TYPE: &str = "image/jpeg";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Has<ImageAttributes>,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_state, has_attributes, opt_children) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttImageConfiguration>(payload.payload.clone())
+        {
+            Ok(config) if config.image_topic.is_some() == config.url_topic.is_some() => {
+                warn!(
+                    "invalid image config {}: exactly one of image_topic and url_topic is required",
+                    hash
+                );
+                continue;
+            }
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid image config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            [&config.image_topic, &config.url_topic]
+                .into_iter()
+                .flatten()
+                .map(String::as_str),
+            config.qos.unwrap_or(0),
+        );
+        let mut cmds = commands.entity(entity);
+        if !has_attributes {
+            cmds.insert(ImageAttributes::default());
+        }
+        cmds.insert((
+            config,
+            MQTTPlatformState,
+            MQTTAttributesBlocked(&MQTT_IMAGE_ATTRIBUTES_BLOCKED),
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageEncoding {
+    #[serde(rename = "b64")]
+    Base64,
+}
+
+/// Decode an image payload, raw payloads are used as is.
+pub(crate) fn decode_image(
+    payload: &Bytes,
+    encoding: Option<ImageEncoding>,
+) -> anyhow::Result<Bytes> {
+    match encoding {
+        None => Ok(payload.clone()),
+        Some(ImageEncoding::Base64) => {
+            let data = STANDARD.decode(payload.trim_ascii())?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttImageConfiguration {
+    /// Receives the image data, cannot be combined with `url_topic`
+    pub image_topic: Option<String>,
+    /// Receives the URL of the image, cannot be combined with `image_topic`
+    pub url_topic: Option<String>,
+    pub url_template: Option<String>,
+    pub content_type: Option<String>,
+    pub image_encoding: Option<ImageEncoding>,
+    pub qos: Option<u8>,
+}
+
+impl MqttImageConfiguration {
+    pub fn content_type(&self) -> &str {
+        self.content_type.as_deref().unwrap_or(DEFAULT_CONTENT_TYPE)
+    }
+}
+
+/// The latest frame received by an image or camera entity.
+#[derive(Debug, Component, Clone)]
+pub struct ImageFrame {
+    pub content: Bytes,
+    pub content_type: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl ImageFrame {
+    pub fn new(content: Bytes, content_type: impl Into<String>) -> Self {
+        Self {
+            content,
+            content_type: content_type.into(),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct ImageAttributes {
+    /// Set when the image is received through `url_topic`
+    pub image_url: Option<String>,
+    #[serde(skip)]
+    pub image_last_updated: Option<DateTime<Utc>>,
+}
+
+/// Retrieve the latest frame of image and camera entities.
+#[derive(SystemParam)]
+pub struct ImageStore<'w, 's> {
+    q_frame: Query<'w, 's, &'static ImageFrame>,
+    q_attributes: Query<'w, 's, &'static ImageAttributes>,
+}
+
+impl<'w, 's> ImageStore<'w, 's> {
+    pub fn frame(&self, entity: Entity) -> Option<&ImageFrame> {
+        self.q_frame.get(entity).ok()
+    }
+
+    pub fn url(&self, entity: Entity) -> Option<&str> {
+        self.q_attributes
+            .get(entity)
+            .ok()
+            .and_then(|attributes| attributes.image_url.as_deref())
+    }
+}
+
+fn handle_image_state(
+    topic_message: Trigger<TopicMessage>,
+    mut commands: Commands,
+    mut q_image: Query<(&MqttImageConfiguration, &mut State, &mut ImageAttributes)>,
+) {
+    let entity = topic_message.entity();
+    let Ok((config, mut state, mut attributes)) = q_image.get_mut(entity) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    let payload = &topic_message.event().payload;
+
+    if config.image_topic.as_deref() == Some(topic) {
+        match decode_image(payload, config.image_encoding) {
+            Ok(content) => {
+                let frame = ImageFrame::new(content, config.content_type());
+                attributes.image_last_updated = Some(frame.timestamp);
+                state.update(frame.timestamp.to_rfc3339());
+                commands.entity(entity).insert(frame);
+            }
+            Err(e) => warn!("Ignoring image payload on {}: {}", topic, e),
+        }
+    } else if config.url_topic.as_deref() == Some(topic) {
+        let url = try_render_template(&config.url_template, payload).unwrap_or_default();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            warn!("Ignoring invalid image url {:?} on {}", url, topic);
+            return;
+        }
+        let now = Utc::now();
+        attributes.image_url = Some(url);
+        attributes.image_last_updated = Some(now);
+        state.update(now.to_rfc3339());
+    }
+}
+
+#[test]
+fn test_image_configuration() {
+    let json = r#"
+    {
+        "image_topic": "doorbell/snapshot",
+        "content_type": "image/png",
+        "image_encoding": "b64"
+    }
+    "#;
+    let config: MqttImageConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.content_type(), "image/png");
+    assert_eq!(config.image_encoding, Some(ImageEncoding::Base64));
+
+    let payload = Bytes::from_static(b"iVBORw0KGgo=\n");
+    assert_eq!(
+        decode_image(&payload, config.image_encoding).unwrap(),
+        Bytes::from_static(b"\x89PNG\r\n\x1a\n")
+    );
+    assert!(decode_image(&Bytes::from_static(b"not base64!"), config.image_encoding).is_err());
+    assert_eq!(decode_image(&payload, None).unwrap(), payload);
+
+    let config: MqttImageConfiguration =
+        serde_json::from_str(r#"{"url_topic": "doorbell/snapshot_url"}"#).unwrap();
+    assert_eq!(config.content_type(), DEFAULT_CONTENT_TYPE);
+}
@@ -0,0 +1,288 @@
+use crate::{
+    constants::PAYLOAD_NONE,
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_template, try_render_template, MQTTPlatformState, MqttPublisher},
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::Children;
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use skep_core::{constants::STATE_UNKNOWN, states::State};
+use std::str::FromStr;
+use strum_macros::{Display, EnumString, IntoStaticStr};
+
+pub struct MqttLawnMowerPlugin;
+
+impl Plugin for MqttLawnMowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_lawn_mower_state)
+        .observe(on_lawn_mower_command);
+    }
+}
+
+const DOMAIN: &str = "lawn_mower";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_state, opt_children) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttLawnMowerConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid lawn_mower config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        // A lawn mower has no state_topic, so the activity topic is subscribed here
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            config.activity_state_topic.as_deref(),
+            config.qos.unwrap_or(0),
+        );
+        let mut cmds = commands.entity(entity);
+        cmds.insert((config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+/// The activity of a lawn mower, stored as its [`State`].
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumString,
+    Display,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum LawnMowerActivity {
+    Mowing,
+    Docked,
+    Paused,
+    Returning,
+    Error,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttLawnMowerConfiguration {
+    pub activity_state_topic: Option<String>,
+    pub activity_value_template: Option<String>,
+    pub start_mowing_command_topic: Option<String>,
+    pub start_mowing_command_template: Option<String>,
+    pub pause_command_topic: Option<String>,
+    pub pause_command_template: Option<String>,
+    pub dock_command_topic: Option<String>,
+    pub dock_command_template: Option<String>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttLawnMowerConfiguration {
+    pub fn optimistic(&self) -> bool {
+        self.optimistic.unwrap_or(false) || self.activity_state_topic.is_none()
+    }
+
+    /// Map a rendered activity payload to a state, `None` resets it to unknown.
+    pub fn parse_activity(&self, payload: &str) -> Option<&'static str> {
+        if payload == PAYLOAD_NONE || payload.is_empty() {
+            return Some(STATE_UNKNOWN);
+        }
+        LawnMowerActivity::from_str(payload.trim())
+            .ok()
+            .map(Into::into)
+    }
+}
+
+/// Commands accepted by an MQTT lawn mower entity, triggered with the lawn mower as target.
+#[derive(Debug, Event, Clone, Copy, PartialEq, Eq)]
+pub enum LawnMowerCommand {
+    StartMowing,
+    Pause,
+    Dock,
+}
+
+fn handle_lawn_mower_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_lawn_mower: Query<(&MqttLawnMowerConfiguration, &mut State)>,
+) {
+    let Ok((config, mut state)) = q_lawn_mower.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.activity_state_topic.as_deref() != Some(topic) {
+        return;
+    }
+    let value = try_render_template(
+        &config.activity_value_template,
+        &topic_message.event().payload,
+    )
+    .unwrap_or_default();
+    match config.parse_activity(&value) {
+        Some(activity) => state.update(activity),
+        None => warn!("Ignoring lawn_mower activity {:?} on {}", value, topic),
+    }
+}
+
+fn on_lawn_mower_command(
+    trigger: Trigger<LawnMowerCommand>,
+    publisher: MqttPublisher,
+    mut q_lawn_mower: Query<(&MqttLawnMowerConfiguration, &mut State)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state)) = q_lawn_mower.get_mut(entity) else {
+        return;
+    };
+
+    let (topic, template, value, activity) = match trigger.event() {
+        LawnMowerCommand::StartMowing => (
+            &config.start_mowing_command_topic,
+            &config.start_mowing_command_template,
+            "start_mowing",
+            LawnMowerActivity::Mowing,
+        ),
+        LawnMowerCommand::Pause => (
+            &config.pause_command_topic,
+            &config.pause_command_template,
+            "pause",
+            LawnMowerActivity::Paused,
+        ),
+        LawnMowerCommand::Dock => (
+            &config.dock_command_topic,
+            &config.dock_command_template,
+            "dock",
+            LawnMowerActivity::Docked,
+        ),
+    };
+    let Some(topic) = topic else {
+        warn!(
+            "Lawn mower {:?} has no topic for {:?}",
+            entity,
+            trigger.event()
+        );
+        return;
+    };
+
+    let payload = match try_render_command_template(template, &json!(value)) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!("Failed to render lawn_mower command: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = publisher.publish(entity, topic, config.qos, config.retain, payload) {
+        warn!("Failed to publish lawn_mower command: {}", e);
+        return;
+    }
+    if config.optimistic() {
+        state.update(activity);
+    }
+}
+
+#[test]
+fn test_lawn_mower_configuration() {
+    let json = r#"
+    {
+        "activity_state_topic": "mower/activity",
+        "activity_value_template": "{{ value_json.activity }}",
+        "start_mowing_command_topic": "mower/start",
+        "dock_command_topic": "mower/dock",
+        "dock_command_template": "{\"action\": \"{{ value }}\"}"
+    }
+    "#;
+    let config: MqttLawnMowerConfiguration = serde_json::from_str(json).unwrap();
+
+    assert!(!config.optimistic());
+    assert_eq!(
+        config.parse_activity(
+            &try_render_template(
+                &config.activity_value_template,
+                br#"{"activity": "mowing"}"#
+            )
+            .unwrap()
+        ),
+        Some("mowing")
+    );
+    assert_eq!(config.parse_activity("None"), Some(STATE_UNKNOWN));
+    assert_eq!(config.parse_activity("sleeping"), None);
+    assert_eq!(
+        try_render_command_template(&config.dock_command_template, &json!("dock")).unwrap(),
+        r#"{"action": "dock"}"#
+    );
+}
+
+#[test]
+fn test_lawn_mower_activity_subscription() {
+    use bevy_ecs::system::RunSystemOnce;
+
+    let mut world = World::new();
+    let hash = MQTTDiscoveryHash {
+        component: DOMAIN.to_string(),
+        discovery_id: "garden_mower".to_string(),
+    };
+    let entity = world
+        .spawn((
+            hash.clone(),
+            MQTTDiscoveryPayload {
+                topic: "lawn_mower/garden_mower/config".to_string(),
+                hash,
+                payload: json!({"activity_state_topic": "mower/activity", "qos": 1}),
+                platform: "mqtt".to_string(),
+            },
+        ))
+        .id();
+    world.run_system_once(create_or_update_discovery_payload);
+
+    let children = world.get::<Children>(entity).unwrap();
+    assert_eq!(children.len(), 1);
+    let sub_topic = world.get::<SubscribeTopic>(children[0]).unwrap();
+    assert_eq!(sub_topic.topic(), "mower/activity");
+    assert!(world.get::<MQTTPlatformTopic>(children[0]).is_some());
+    assert_eq!(
+        world.get::<State>(entity).unwrap().state,
+        STATE_UNKNOWN.to_string()
+    );
+}
@@ -1,18 +1,57 @@
 use crate::{
+    alarm_control_panel::MqttAlarmControlPanelPlugin,
     binary_sensor::MqttBinarySensorPlugin,
-    constants::DOMAIN,
+    button::MqttButtonPlugin,
+    camera::MqttCameraPlugin,
+    climate::MqttClimatePlugin,
+    constants::{
+        DEFAULT_CAPACITY, DEFAULT_KEEPALIVE, DEFAULT_PREFIX, DEFAULT_WS_PATH, DOMAIN, PROTOCOL_311,
+        PROTOCOL_5,
+    },
+    cover::MqttCoverPlugin,
+    device_automation::MqttDeviceAutomationPlugin,
+    device_tracker::MqttDeviceTrackerPlugin,
     discovery::{
-        on_mqtt_message_received, setup_new_entity_from_discovery, sub_default_topic,
-        update_entity_from_discovery, MQTTDiscoveryHash, MQTTDiscoveryNew, MQTTDiscoveryPayload,
-        MQTTDiscoveryUpdate, MQTTSupportComponent, ProcessDiscoveryPayload,
+        finish_pending_discovery, on_mqtt_message_received, remove_entity_from_discovery,
+        setup_new_entity_from_discovery, sub_default_topic, update_entity_from_discovery,
+        MQTTDiscoveryDone, MQTTDiscoveryHash, MQTTDiscoveryNew, MQTTDiscoveryPayload,
+        MQTTDiscoveryRemove, MQTTDiscoveryUpdate, MQTTSupportComponent, ProcessDiscoveryPayload,
+    },
+    entity::{
+        handle_json_attributes, MQTTAvailability, MQTTAvailabilityConfiguration,
+        MQTTJsonAttributesConfiguration,
     },
-    entity::{MQTTAvailability, MQTTAvailabilityConfiguration},
+    event::MqttEventPlugin,
+    fan::MqttFanPlugin,
+    humidifier::MqttHumidifierPlugin,
+    image::MqttImagePlugin,
+    lawn_mower::MqttLawnMowerPlugin,
+    light::MqttLightPlugin,
+    lock::MqttLockPlugin,
+    notify::MqttNotifyPlugin,
+    number::MqttNumberPlugin,
+    scene::MqttScenePlugin,
+    select::MqttSelectPlugin,
     sensor::MqttSensorPlugin,
-    subscription::{add_state_subscription, update_available_subscription, MQTTStateSubscription},
+    siren::MqttSirenPlugin,
+    status::{publish_birth_message, MqttStatusMessages},
+    subscription::{
+        add_state_subscription, update_available_subscription, update_json_attributes_subscription,
+        MQTTJsonAttributesTopic, MQTTPlatformTopic, MQTTStateSubscription,
+    },
+    switch::MqttSwitchPlugin,
+    tag::MqttTagPlugin,
+    text::MqttTextPlugin,
+    tls::tls_configuration,
+    update::MqttUpdatePlugin,
+    vacuum::MqttVacuumPlugin,
+    valve::MqttValvePlugin,
+    water_heater::MqttWaterHeaterPlugin,
 };
 use bevy_app::prelude::*;
 use bevy_core::Name;
 use bevy_ecs::prelude::*;
+use bevy_log::error;
 use bevy_mqtt::{rumqttc, MqttClientError, MqttConnectError, MqttPlugin, MqttSetting};
 use bevy_reflect::Reflect;
 use bevy_state::app::StatesPlugin;
@@ -23,16 +62,87 @@ use skep_core::{
     integration::Integration, loader::LoadConfig, platform::Platform, typing::ConfigType,
     CallbackType,
 };
-use std::collections::VecDeque;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
 
 mod abbreviations;
+mod alarm_control_panel;
 mod binary_sensor;
+mod button;
+mod camera;
+mod climate;
 mod constants;
+mod cover;
+mod device_automation;
+mod device_tracker;
 mod discovery;
 mod entity;
+mod event;
+mod fan;
+mod humidifier;
+mod image;
+mod lawn_mower;
+mod light;
+mod lock;
 mod models;
+mod notify;
+mod number;
+mod scene;
+mod select;
 mod sensor;
+mod siren;
+mod status;
 mod subscription;
+mod switch;
+mod tag;
+mod text;
+mod tls;
+mod update;
+mod vacuum;
+mod valve;
+mod water_heater;
+
+pub use alarm_control_panel::{
+    AlarmArmMode, AlarmControlPanelAttributes, AlarmControlPanelCommand, AlarmControlPanelFeature,
+    CodeFormat, MqttAlarmControlPanelConfiguration,
+};
+pub use binary_sensor::{BinarySensorDeviceClass, MqttBinarySensorConfiguration};
+pub use button::{ButtonCommand, ButtonDeviceClass, MqttButtonConfiguration};
+pub use camera::MqttCameraConfiguration;
+pub use climate::{
+    ClimateAttributes, ClimateCommand, HvacAction, HvacMode, MqttClimateConfiguration,
+};
+pub use cover::{CoverAttributes, CoverCommand, MqttCoverConfiguration};
+pub use device_automation::{AutomationType, DeviceTrigger, MqttDeviceAutomationConfiguration};
+pub use device_tracker::{DeviceTrackerAttributes, MqttDeviceTrackerConfiguration, SourceType};
+pub use event::{EventAttributes, EventDeviceClass, MqttEventConfiguration};
+pub use fan::{FanAttributes, FanCommand, FanDirection, MqttFanConfiguration};
+pub use humidifier::{
+    HumidifierAction, HumidifierAttributes, HumidifierCommand, HumidifierDeviceClass,
+    MqttHumidifierConfiguration,
+};
+pub use image::{ImageAttributes, ImageEncoding, ImageFrame, ImageStore, MqttImageConfiguration};
+pub use lawn_mower::{LawnMowerActivity, LawnMowerCommand, MqttLawnMowerConfiguration};
+pub use light::{
+    ColorMode, LightAttributes, LightCommand, LightFlash, LightTurnOn, MqttLightConfiguration,
+};
+pub use lock::{LockCommand, MqttLockConfiguration};
+pub use notify::MqttNotifyConfiguration;
+pub use number::{MqttNumberConfiguration, NumberAttributes, NumberCommand, NumberMode};
+pub use scene::{MqttSceneConfiguration, SceneCommand};
+pub use select::{MqttSelectConfiguration, SelectAttributes, SelectCommand};
+pub use sensor::MqttSensorConfiguration;
+pub use siren::{MqttSirenConfiguration, SirenAttributes, SirenCommand, SirenTurnOn};
+pub use status::{MqttMessage, MqttMessageConfig, MqttStatusMessages};
+pub use switch::{MqttSwitchConfiguration, SwitchCommand};
+pub use tag::MqttTagConfiguration;
+pub use text::{MqttTextConfiguration, TextAttributes, TextCommand, TextMode};
+pub use update::{MqttUpdateConfiguration, UpdateAttributes, UpdateCommand, UpdateDeviceClass};
+pub use vacuum::{MqttVacuumConfiguration, VacuumAttributes, VacuumCommand, VacuumFeature};
+pub use valve::{MqttValveConfiguration, ValveAttributes, ValveCommand};
+pub use water_heater::{MqttWaterHeaterConfiguration, WaterHeaterAttributes, WaterHeaterCommand};
 
 type DiscoveryInfoType = Map<String, Value>;
 
@@ -50,10 +160,15 @@ impl Plugin for SkepMqttPlugin {
             .register_type::<MQTTSupportComponent>()
             .register_type::<MQTTAvailability>()
             .register_type::<MQTTStateSubscription>()
+            .register_type::<MQTTPlatformTopic>()
+            .register_type::<MQTTJsonAttributesConfiguration>()
+            .register_type::<MQTTJsonAttributesTopic>()
             .register_type::<HashSet<(String, String)>>()
             .add_event::<ProcessDiscoveryPayload>()
             .add_event::<MQTTDiscoveryNew>()
             .add_event::<MQTTDiscoveryUpdate>()
+            .add_event::<MQTTDiscoveryRemove>()
+            .add_event::<MQTTDiscoveryDone>()
             .add_systems(Startup, setup)
             .add_systems(
                 Update,
@@ -63,9 +178,43 @@ impl Plugin for SkepMqttPlugin {
                     handle_error,
                     add_state_subscription,
                     update_available_subscription,
+                    update_json_attributes_subscription,
+                    publish_birth_message.after(sub_default_topic),
                 ),
             )
-            .add_plugins((MqttSensorPlugin, MqttBinarySensorPlugin))
+            .add_plugins((
+                (
+                    MqttSensorPlugin,
+                    MqttBinarySensorPlugin,
+                    MqttSwitchPlugin,
+                    MqttLightPlugin,
+                    MqttCoverPlugin,
+                    MqttClimatePlugin,
+                    MqttFanPlugin,
+                    MqttHumidifierPlugin,
+                    MqttLockPlugin,
+                    MqttValvePlugin,
+                ),
+                (
+                    MqttNumberPlugin,
+                    MqttSelectPlugin,
+                    MqttTextPlugin,
+                    MqttButtonPlugin,
+                    MqttScenePlugin,
+                    MqttSirenPlugin,
+                    MqttAlarmControlPanelPlugin,
+                    MqttWaterHeaterPlugin,
+                    MqttVacuumPlugin,
+                    MqttLawnMowerPlugin,
+                    MqttDeviceTrackerPlugin,
+                    MqttDeviceAutomationPlugin,
+                    MqttEventPlugin,
+                    MqttTagPlugin,
+                    MqttUpdatePlugin,
+                ),
+                (MqttImagePlugin, MqttCameraPlugin, MqttNotifyPlugin),
+            ))
+            .observe(handle_json_attributes)
             .observe(reload_config);
     }
 }
@@ -93,8 +242,14 @@ pub(crate) struct SkepMqttPlatform {
     pub discovery_prefix: String,
     pub discovered: HashMap<MQTTDiscoveryHash, Entity>,
     pub discovery_already_discovered: HashSet<MQTTDiscoveryHash>,
+    /// The components of each device discovery config, by discovery id
+    #[reflect(ignore)]
+    pub discovery_device_components: HashMap<String, HashSet<MQTTDiscoveryHash>>,
     #[reflect(ignore)]
     pub discovery_pending_discovered: HashMap<MQTTDiscoveryHash, PendingDiscovered>,
+    /// Hash of the last config payload of each discovered entity, to skip republished configs
+    #[reflect(ignore)]
+    pub discovery_payload_hashes: HashMap<MQTTDiscoveryHash, u64>,
     #[reflect(ignore)]
     pub discovery_registry_hooks: HashMap<(String, String), CallbackType>,
     pub platforms_loaded: HashSet<String>,
@@ -106,10 +261,12 @@ impl Default for SkepMqttPlatform {
     fn default() -> Self {
         Self {
             last_discovery: Default::default(),
-            discovery_prefix: "homeassistant".to_string(),
+            discovery_prefix: DEFAULT_PREFIX.to_string(),
             discovered: Default::default(),
             discovery_already_discovered: Default::default(),
+            discovery_device_components: Default::default(),
             discovery_pending_discovered: Default::default(),
+            discovery_payload_hashes: Default::default(),
             discovery_registry_hooks: Default::default(),
             platforms_loaded: Default::default(),
             config: vec![],
@@ -117,7 +274,10 @@ impl Default for SkepMqttPlatform {
     }
 }
 
-#[derive(Debug)]
+/// A discovered entity that is still being set up. A hash is unknown until its first config, in
+/// progress while it has a [`PendingDiscovered`], and discovered once setup has finished. Configs
+/// received in progress are queued newest first, only the newest one is applied afterwards.
+#[derive(Debug, Default)]
 pub struct PendingDiscovered {
     pub pending: VecDeque<MQTTDiscoveryPayload>,
 }
@@ -126,10 +286,34 @@ pub struct PendingDiscovered {
 pub struct MqttConfig {
     /// mqtt broker host
     pub broker: String,
+    /// mqtt client id, default a generated unique id so clients don't take over each other's session
+    pub client_id: Option<String>,
+    /// Keep alive interval in seconds, default 60
+    pub keepalive: Option<u64>,
+    /// Start a clean session on connect, default true
+    pub clean_session: Option<bool>,
+    /// Maximum number of outgoing QoS 1/2 messages in flight
+    pub max_inflight: Option<u16>,
+    /// mqtt protocol version, `"3.1.1"` (default). `"5"` is a config error, the broker is not
+    /// connected until bevy_mqtt provides an MQTT v5 client
+    pub protocol: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// CA certificate file used to verify the broker, or `auto` for the public CA roots, enables
+    /// TLS
+    pub certificate: Option<String>,
+    /// Client private key file for mutual TLS, requires `client_cert`
     pub client_key: Option<String>,
+    /// Client certificate file for mutual TLS, requires `client_key`
     pub client_cert: Option<String>,
-    /// mqtt client transport
+    /// Skip the verification of the broker certificate, enables TLS
+    pub tls_insecure: Option<bool>,
+    /// mqtt client transport, `tcp` or `ws`, over TLS when a certificate or `tls_insecure` is set
     pub transport: Option<String>,
+    /// websocket request path, default `/`
+    pub ws_path: Option<String>,
+    /// Extra headers of the websocket request
+    pub ws_headers: Option<BTreeMap<String, String>>,
     /// mqtt broker port
     pub port: u16,
     /// Request channel capacity
@@ -138,6 +322,89 @@ pub struct MqttConfig {
     pub auto_discovery: Option<bool>,
     /// default discovery prefix topic: homeassistant
     pub discovery_prefix: Option<String>,
+    /// Published on connect, default `online` to `<discovery_prefix>/status`
+    pub birth_message: Option<MqttMessageConfig>,
+    /// Set as last will, sent by the broker when the connection is lost, default `offline` to
+    /// `<discovery_prefix>/status`
+    pub will_message: Option<MqttMessageConfig>,
+}
+
+impl MqttConfig {
+    /// The client options of this broker, everything but the last will.
+    pub fn mqtt_options(&self) -> anyhow::Result<rumqttc::MqttOptions> {
+        match self.protocol.as_deref() {
+            None | Some(PROTOCOL_311) => {}
+            Some(PROTOCOL_5) => {
+                return Err(anyhow::anyhow!(
+                    "mqtt protocol 5 is not supported, bevy_mqtt only provides an MQTT 3.1.1 client"
+                ))
+            }
+            Some(protocol) => return Err(anyhow::anyhow!("unknown mqtt protocol {}", protocol)),
+        }
+
+        let tls = tls_configuration(self)?;
+        let websocket = matches!(self.transport.as_deref(), Some("ws" | "websocket"));
+        // The websocket transport connects to an url instead of a host
+        let broker_addr = if websocket {
+            format!(
+                "{}://{}:{}{}",
+                if tls.is_some() { "wss" } else { "ws" },
+                self.broker,
+                self.port,
+                self.ws_path.as_deref().unwrap_or(DEFAULT_WS_PATH)
+            )
+        } else {
+            self.broker.clone()
+        };
+        // An empty client id is rejected by the client, treat it as unset
+        let client_id = match self.client_id.as_deref() {
+            Some(client_id) if !client_id.is_empty() => client_id.to_string(),
+            _ => format!("skep-{}", &uuid::Uuid::new_v4().simple().to_string()[..16]),
+        };
+
+        let mut mqtt_options = rumqttc::MqttOptions::new(client_id, broker_addr, self.port);
+        mqtt_options
+            .set_keep_alive(Duration::from_secs(
+                self.keepalive.unwrap_or(DEFAULT_KEEPALIVE),
+            ))
+            .set_clean_session(self.clean_session.unwrap_or(true));
+        match self.max_inflight {
+            Some(0) => return Err(anyhow::anyhow!("max_inflight must be at least 1")),
+            Some(max_inflight) => {
+                mqtt_options.set_inflight(max_inflight);
+            }
+            None => {}
+        }
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            mqtt_options.set_credentials(username, password);
+        }
+        if let Some(ws_headers) = &self.ws_headers {
+            let headers = ws_headers
+                .iter()
+                .map(|(name, value)| {
+                    Ok((
+                        http::HeaderName::from_bytes(name.as_bytes())?,
+                        http::HeaderValue::from_str(value)?,
+                    ))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            mqtt_options.set_request_modifier(move |mut request: http::Request<()>| {
+                let headers = headers.clone();
+                async move {
+                    request.headers_mut().extend(headers);
+                    request
+                }
+            });
+        }
+
+        mqtt_options.set_transport(match (websocket, tls) {
+            (true, Some(tls)) => rumqttc::Transport::Wss(tls),
+            (true, None) => rumqttc::Transport::Ws,
+            (false, Some(tls)) => rumqttc::Transport::Tls(tls),
+            (false, None) => rumqttc::Transport::Tcp,
+        });
+        Ok(mqtt_options)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -157,26 +424,25 @@ pub fn reload_config(trigger: Trigger<LoadConfig>, mut commands: Commands) {
         }
         if let Ok(config) = serde_json::from_value::<MqttLoader>(mqtt_config.clone()) {
             for config_entry in config.mqtt_config_entry {
-                let mut mqtt_options = rumqttc::MqttOptions::new(
-                    "skep-client",
-                    &config_entry.broker,
-                    config_entry.port,
-                );
-                if let (Some(username), Some(password)) =
-                    (config_entry.client_key, config_entry.client_cert)
-                {
-                    mqtt_options.set_credentials(username, password);
-                }
-                let transport = match config_entry.transport {
-                    None => rumqttc::Transport::Tcp,
-                    Some(s) => match s.as_str() {
-                        "tcp" => rumqttc::Transport::Tcp,
-                        "ws" | "websocket" => rumqttc::Transport::Ws,
-                        _ => rumqttc::Transport::Tcp,
-                    },
+                let mut mqtt_options = match config_entry.mqtt_options() {
+                    Ok(mqtt_options) => mqtt_options,
+                    Err(e) => {
+                        error!("invalid mqtt config for {}: {}", config_entry.broker, e);
+                        continue;
+                    }
                 };
 
-                mqtt_options.set_transport(transport);
+                let discovery_prefix = config_entry
+                    .discovery_prefix
+                    .unwrap_or_else(|| DEFAULT_PREFIX.to_string());
+                let status_messages = MqttStatusMessages::from_config(
+                    config_entry.birth_message,
+                    config_entry.will_message,
+                    &discovery_prefix,
+                );
+                if let Some(will) = &status_messages.will {
+                    mqtt_options.set_last_will(will.last_will());
+                }
 
                 commands
                     .spawn((
@@ -188,15 +454,94 @@ pub fn reload_config(trigger: Trigger<LoadConfig>, mut commands: Commands) {
                         Platform::new(format!("{}:{}", config_entry.broker, config_entry.port)),
                         MqttSetting {
                             mqtt_options,
-                            cap: 20,
+                            cap: config_entry.capacity.unwrap_or(DEFAULT_CAPACITY),
                         },
-                        SkepMqttPlatform::default(),
+                        SkepMqttPlatform {
+                            discovery_prefix,
+                            ..Default::default()
+                        },
+                        status_messages,
                     ))
                     .observe(setup_new_entity_from_discovery)
-                    .observe(update_entity_from_discovery);
+                    .observe(update_entity_from_discovery)
+                    .observe(remove_entity_from_discovery)
+                    .observe(finish_pending_discovery);
             }
         }
     }
 }
 
 pub fn on_setup_config_entry() {}
+
+#[test]
+fn test_mqtt_options() {
+    let config: MqttConfig = serde_json::from_value(serde_json::json!({
+        "broker": "localhost",
+        "port": 1883,
+        "username": "skep",
+        "password": "secret"
+    }))
+    .unwrap();
+    let mqtt_options = config.mqtt_options().unwrap();
+    assert!(mqtt_options.client_id().starts_with("skep-"));
+    assert_ne!(
+        mqtt_options.client_id(),
+        config.mqtt_options().unwrap().client_id()
+    );
+    assert_eq!(mqtt_options.keep_alive(), Duration::from_secs(60));
+    assert!(mqtt_options.clean_session());
+    assert_eq!(
+        mqtt_options.credentials(),
+        Some(("skep".to_string(), "secret".to_string()))
+    );
+
+    let config: MqttConfig = serde_json::from_value(serde_json::json!({
+        "broker": "localhost",
+        "port": 8080,
+        "client_id": "skep-kitchen",
+        "keepalive": 30,
+        "clean_session": false,
+        "max_inflight": 10,
+        "transport": "websocket",
+        "ws_path": "/mqtt",
+        "ws_headers": {"Authorization": "Bearer token"}
+    }))
+    .unwrap();
+    let mqtt_options = config.mqtt_options().unwrap();
+    assert_eq!(mqtt_options.client_id(), "skep-kitchen");
+    assert_eq!(mqtt_options.keep_alive(), Duration::from_secs(30));
+    assert!(!mqtt_options.clean_session());
+    assert_eq!(mqtt_options.inflight(), 10);
+    assert_eq!(
+        mqtt_options.broker_address(),
+        ("ws://localhost:8080/mqtt".to_string(), 8080)
+    );
+
+    let config: MqttConfig = serde_json::from_value(serde_json::json!({
+        "broker": "localhost",
+        "port": 1883,
+        "protocol": "5"
+    }))
+    .unwrap();
+    assert!(config.mqtt_options().is_err());
+
+    let config: MqttConfig = serde_json::from_value(serde_json::json!({
+        "broker": "localhost",
+        "port": 1883,
+        "client_id": ""
+    }))
+    .unwrap();
+    assert!(config
+        .mqtt_options()
+        .unwrap()
+        .client_id()
+        .starts_with("skep-"));
+
+    let config: MqttConfig = serde_json::from_value(serde_json::json!({
+        "broker": "localhost",
+        "port": 1883,
+        "max_inflight": 0
+    }))
+    .unwrap();
+    assert!(config.mqtt_options().is_err());
+}
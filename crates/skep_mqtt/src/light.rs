@@ -0,0 +1,1091 @@
+use crate::{
+    constants::{DEFAULT_PAYLOAD_OFF, DEFAULT_PAYLOAD_ON, PAYLOAD_NONE},
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{
+        try_render_command_template, try_render_command_variables, try_render_template,
+        MQTTAttributesBlocked, MQTTPlatformState, MqttPublisher,
+    },
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::Children;
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use skep_core::{
+    constants::{STATE_OFF, STATE_ON, STATE_UNKNOWN},
+    states::{State, StateAttributes},
+};
+use std::collections::HashSet;
+use strum_macros::{Display, EnumString};
+
+lazy_static! {
+    static ref MQTT_LIGHT_ATTRIBUTES_BLOCKED: HashSet<&'static str> = HashSet::from([
+        "brightness",
+        "color_mode",
+        "color_temp",
+        "effect",
+        "effect_list",
+        "hs_color",
+        "max_mireds",
+        "min_mireds",
+        "rgb_color",
+        "rgbw_color",
+        "rgbww_color",
+        "supported_color_modes",
+        "xy_color",
+    ]);
+}
+
+pub struct MqttLightPlugin;
+
+impl Plugin for MqttLightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_light_state)
+        .observe(on_light_command);
+    }
+}
+
+const DOMAIN: &str = "light";
+
+const DEFAULT_BRIGHTNESS_SCALE: u32 = 255;
+const DEFAULT_WHITE_SCALE: u32 = 255;
+const DEFAULT_MIN_MIREDS: u16 = 153;
+const DEFAULT_MAX_MIREDS: u16 = 500;
+const DEFAULT_FLASH_TIME_SHORT: u32 = 2;
+const DEFAULT_FLASH_TIME_LONG: u32 = 10;
+
+pub const SUPPORT_EFFECT: i32 = 4;
+pub const SUPPORT_FLASH: i32 = 8;
+pub const SUPPORT_TRANSITION: i32 = 32;
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    mut q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&mut StateAttributes>,
+            Option<&mut LightAttributes>,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_state, opt_attributes, opt_light_attributes, opt_children) in
+        q_discovery.iter_mut()
+    {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttLightConfiguration>(payload.payload.clone())
+        {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid light config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            config.extra_state_topics(),
+            config.qos.unwrap_or(0),
+        );
+        if let Some(mut attributes) = opt_attributes {
+            attributes.supported_features = Some(config.supported_features());
+        }
+
+        let mut cmds = commands.entity(entity);
+        match opt_light_attributes {
+            Some(mut light_attributes) => light_attributes.update_from_config(&config),
+            None => {
+                let mut light_attributes = LightAttributes::default();
+                light_attributes.update_from_config(&config);
+                cmds.insert(light_attributes);
+            }
+        }
+        cmds.insert((
+            config,
+            MQTTPlatformState,
+            MQTTAttributesBlocked(&MQTT_LIGHT_ATTRIBUTES_BLOCKED),
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LightSchema {
+    #[default]
+    #[serde(alias = "default")]
+    Basic,
+    Json,
+    Template,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OnCommandType {
+    /// Send `payload_on` before any of the attribute commands
+    First,
+    /// Send `payload_on` after the attribute commands
+    #[default]
+    Last,
+    /// Only send the brightness command
+    Brightness,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[strum(ascii_case_insensitive)]
+pub enum ColorMode {
+    Unknown,
+    Onoff,
+    Brightness,
+    ColorTemp,
+    Hs,
+    Xy,
+    Rgb,
+    Rgbw,
+    Rgbww,
+    White,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LightFlash {
+    Short,
+    Long,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttLightConfiguration {
+    /// The schema to use, `basic` (the default), `json` or `template`.
+    pub schema: Option<LightSchema>,
+    pub command_topic: Option<String>,
+    pub state_topic: Option<String>,
+    pub state_value_template: Option<String>,
+    pub payload_on: Option<String>,
+    pub payload_off: Option<String>,
+    pub on_command_type: Option<OnCommandType>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+
+    /// Flag that defines if the JSON schema light supports brightness.
+    pub brightness: Option<bool>,
+    pub brightness_scale: Option<u32>,
+    pub brightness_command_topic: Option<String>,
+    pub brightness_command_template: Option<String>,
+    pub brightness_state_topic: Option<String>,
+    pub brightness_value_template: Option<String>,
+    pub color_mode_state_topic: Option<String>,
+    pub color_mode_value_template: Option<String>,
+    pub color_temp_command_topic: Option<String>,
+    pub color_temp_command_template: Option<String>,
+    pub color_temp_state_topic: Option<String>,
+    pub color_temp_value_template: Option<String>,
+    /// Flag that defines if the JSON schema light supports effects.
+    pub effect: Option<bool>,
+    pub effect_command_topic: Option<String>,
+    pub effect_command_template: Option<String>,
+    pub effect_state_topic: Option<String>,
+    pub effect_value_template: Option<String>,
+    pub effect_list: Option<Vec<String>>,
+    pub hs_command_topic: Option<String>,
+    pub hs_command_template: Option<String>,
+    pub hs_state_topic: Option<String>,
+    pub hs_value_template: Option<String>,
+    pub rgb_command_topic: Option<String>,
+    pub rgb_command_template: Option<String>,
+    pub rgb_state_topic: Option<String>,
+    pub rgb_value_template: Option<String>,
+    pub rgbw_command_topic: Option<String>,
+    pub rgbw_command_template: Option<String>,
+    pub rgbw_state_topic: Option<String>,
+    pub rgbw_value_template: Option<String>,
+    pub rgbww_command_topic: Option<String>,
+    pub rgbww_command_template: Option<String>,
+    pub rgbww_state_topic: Option<String>,
+    pub rgbww_value_template: Option<String>,
+    pub xy_command_topic: Option<String>,
+    pub xy_command_template: Option<String>,
+    pub xy_state_topic: Option<String>,
+    pub xy_value_template: Option<String>,
+    pub white_command_topic: Option<String>,
+    pub white_scale: Option<u32>,
+    pub min_mireds: Option<u16>,
+    pub max_mireds: Option<u16>,
+    pub supported_color_modes: Option<Vec<ColorMode>>,
+    pub flash_time_short: Option<u32>,
+    pub flash_time_long: Option<u32>,
+
+    // Template schema
+    pub command_on_template: Option<String>,
+    pub command_off_template: Option<String>,
+    pub state_template: Option<String>,
+    pub brightness_template: Option<String>,
+    pub color_temp_template: Option<String>,
+    pub effect_template: Option<String>,
+    pub red_template: Option<String>,
+    pub green_template: Option<String>,
+    pub blue_template: Option<String>,
+}
+
+impl MqttLightConfiguration {
+    pub fn schema(&self) -> LightSchema {
+        self.schema.unwrap_or_default()
+    }
+
+    pub fn payload_on(&self) -> &str {
+        self.payload_on.as_deref().unwrap_or(DEFAULT_PAYLOAD_ON)
+    }
+
+    pub fn payload_off(&self) -> &str {
+        self.payload_off.as_deref().unwrap_or(DEFAULT_PAYLOAD_OFF)
+    }
+
+    pub fn optimistic(&self) -> bool {
+        self.optimistic.unwrap_or(self.state_topic.is_none())
+    }
+
+    fn brightness_scale(&self) -> u32 {
+        self.brightness_scale.unwrap_or(DEFAULT_BRIGHTNESS_SCALE)
+    }
+
+    /// State topics besides `state_topic` which are only used by the basic schema.
+    pub fn extra_state_topics(&self) -> Vec<&str> {
+        if self.schema() != LightSchema::Basic {
+            return vec![];
+        }
+
+        [
+            &self.brightness_state_topic,
+            &self.color_mode_state_topic,
+            &self.color_temp_state_topic,
+            &self.effect_state_topic,
+            &self.hs_state_topic,
+            &self.rgb_state_topic,
+            &self.rgbw_state_topic,
+            &self.rgbww_state_topic,
+            &self.xy_state_topic,
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .filter(|topic| Some(*topic) != self.state_topic.as_deref())
+        .collect()
+    }
+
+    pub fn supported_color_modes(&self) -> Vec<ColorMode> {
+        match self.schema() {
+            LightSchema::Basic => {
+                let mut modes = vec![];
+                for (topic, mode) in [
+                    (&self.color_temp_command_topic, ColorMode::ColorTemp),
+                    (&self.hs_command_topic, ColorMode::Hs),
+                    (&self.rgb_command_topic, ColorMode::Rgb),
+                    (&self.rgbw_command_topic, ColorMode::Rgbw),
+                    (&self.rgbww_command_topic, ColorMode::Rgbww),
+                    (&self.white_command_topic, ColorMode::White),
+                    (&self.xy_command_topic, ColorMode::Xy),
+                ] {
+                    if topic.is_some() {
+                        modes.push(mode);
+                    }
+                }
+                if modes.is_empty() {
+                    if self.brightness_command_topic.is_some() {
+                        modes.push(ColorMode::Brightness);
+                    } else {
+                        modes.push(ColorMode::Onoff);
+                    }
+                }
+                modes
+            }
+            LightSchema::Json => match &self.supported_color_modes {
+                Some(modes) if !modes.is_empty() => modes.clone(),
+                _ if self.brightness.unwrap_or(false) => vec![ColorMode::Brightness],
+                _ => vec![ColorMode::Onoff],
+            },
+            LightSchema::Template => {
+                let mut modes = vec![];
+                if self.color_temp_template.is_some() {
+                    modes.push(ColorMode::ColorTemp);
+                }
+                if self.red_template.is_some()
+                    && self.green_template.is_some()
+                    && self.blue_template.is_some()
+                {
+                    modes.push(ColorMode::Hs);
+                }
+                if modes.is_empty() {
+                    if self.brightness_template.is_some() {
+                        modes.push(ColorMode::Brightness);
+                    } else {
+                        modes.push(ColorMode::Onoff);
+                    }
+                }
+                modes
+            }
+        }
+    }
+
+    pub fn supported_features(&self) -> i32 {
+        let mut features = 0;
+        match self.schema() {
+            LightSchema::Basic => {
+                if self.effect_command_topic.is_some() && self.effect_list.is_some() {
+                    features |= SUPPORT_EFFECT;
+                }
+            }
+            LightSchema::Json => {
+                features |= SUPPORT_FLASH | SUPPORT_TRANSITION;
+                if self.effect.unwrap_or(false) {
+                    features |= SUPPORT_EFFECT;
+                }
+            }
+            LightSchema::Template => {
+                features |= SUPPORT_FLASH | SUPPORT_TRANSITION;
+                if self.effect_list.is_some() {
+                    features |= SUPPORT_EFFECT;
+                }
+            }
+        }
+        features
+    }
+
+    fn scale_to_device(&self, brightness: u8) -> u32 {
+        (brightness as f64 / 255.0 * self.brightness_scale() as f64).round() as u32
+    }
+
+    fn scale_from_device(&self, value: f64) -> u8 {
+        (value / self.brightness_scale() as f64 * 255.0)
+            .round()
+            .clamp(0.0, 255.0) as u8
+    }
+
+    fn effect_supported(&self, effect: &str) -> bool {
+        self.effect_list
+            .as_ref()
+            .map_or(true, |list| list.iter().any(|e| e == effect))
+    }
+
+    fn flash_time(&self, flash: LightFlash) -> u32 {
+        match flash {
+            LightFlash::Short => self.flash_time_short.unwrap_or(DEFAULT_FLASH_TIME_SHORT),
+            LightFlash::Long => self.flash_time_long.unwrap_or(DEFAULT_FLASH_TIME_LONG),
+        }
+    }
+
+    /// Build the `(topic, payload)` messages to publish for `command`.
+    pub fn command_messages(
+        &self,
+        command: &LightCommand,
+        attributes: &LightAttributes,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        match (self.schema(), command) {
+            (LightSchema::Basic, LightCommand::TurnOn(params)) => {
+                self.basic_turn_on_messages(params, attributes)
+            }
+            (LightSchema::Json, LightCommand::TurnOn(params)) => self.json_turn_on_messages(params),
+            (LightSchema::Template, LightCommand::TurnOn(params)) => {
+                self.template_turn_on_messages(params)
+            }
+            (LightSchema::Basic, LightCommand::TurnOff { .. }) => Ok(self
+                .command_topic
+                .iter()
+                .map(|topic| (topic.clone(), self.payload_off().to_string()))
+                .collect()),
+            (LightSchema::Json, LightCommand::TurnOff { transition }) => {
+                let mut message = json!({ "state": DEFAULT_PAYLOAD_OFF });
+                if let Some(transition) = transition {
+                    message["transition"] = json!(transition);
+                }
+                Ok(self
+                    .command_topic
+                    .iter()
+                    .map(|topic| (topic.clone(), message.to_string()))
+                    .collect())
+            }
+            (LightSchema::Template, LightCommand::TurnOff { transition }) => {
+                let mut variables = Map::new();
+                variables.insert("state".to_string(), json!(STATE_OFF));
+                if let Some(transition) = transition {
+                    variables.insert("transition".to_string(), json!(transition));
+                }
+                let payload = match &self.command_off_template {
+                    Some(template) => try_render_command_variables(template, &variables)?,
+                    None => self.payload_off().to_string(),
+                };
+                Ok(self
+                    .command_topic
+                    .iter()
+                    .map(|topic| (topic.clone(), payload.clone()))
+                    .collect())
+            }
+            (_, LightCommand::Toggle) => Err(anyhow::anyhow!("Toggle must be resolved first")),
+        }
+    }
+
+    fn basic_turn_on_messages(
+        &self,
+        params: &LightTurnOn,
+        attributes: &LightAttributes,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let mut messages = vec![];
+        let on_command_type = self.on_command_type.unwrap_or_default();
+
+        if let (OnCommandType::First, Some(topic)) = (on_command_type, &self.command_topic) {
+            messages.push((topic.clone(), self.payload_on().to_string()));
+        }
+
+        if let (Some((hue, sat)), Some(topic)) = (params.hs_color, &self.hs_command_topic) {
+            let payload = match &self.hs_command_template {
+                Some(template) => {
+                    let mut variables = Map::new();
+                    variables.insert("hue".to_string(), json!(hue));
+                    variables.insert("sat".to_string(), json!(sat));
+                    try_render_command_variables(template, &variables)?
+                }
+                None => format!("{},{}", hue, sat),
+            };
+            messages.push((topic.clone(), payload));
+        }
+
+        if let (Some((r, g, b)), Some(topic)) = (params.rgb_color, &self.rgb_command_topic) {
+            let payload = match &self.rgb_command_template {
+                Some(template) => {
+                    try_render_command_variables(template, &rgb_variables(&[r, g, b]))?
+                }
+                None => format!("{},{},{}", r, g, b),
+            };
+            messages.push((topic.clone(), payload));
+        }
+
+        if let (Some((r, g, b, w)), Some(topic)) = (params.rgbw_color, &self.rgbw_command_topic) {
+            let payload = match &self.rgbw_command_template {
+                Some(template) => {
+                    try_render_command_variables(template, &rgb_variables(&[r, g, b, w]))?
+                }
+                None => format!("{},{},{},{}", r, g, b, w),
+            };
+            messages.push((topic.clone(), payload));
+        }
+
+        if let (Some((r, g, b, c, w)), Some(topic)) =
+            (params.rgbww_color, &self.rgbww_command_topic)
+        {
+            let payload = match &self.rgbww_command_template {
+                Some(template) => {
+                    try_render_command_variables(template, &rgb_variables(&[r, g, b, c, w]))?
+                }
+                None => format!("{},{},{},{},{}", r, g, b, c, w),
+            };
+            messages.push((topic.clone(), payload));
+        }
+
+        if let (Some((x, y)), Some(topic)) = (params.xy_color, &self.xy_command_topic) {
+            let payload = match &self.xy_command_template {
+                Some(template) => {
+                    let mut variables = Map::new();
+                    variables.insert("x".to_string(), json!(x));
+                    variables.insert("y".to_string(), json!(y));
+                    try_render_command_variables(template, &variables)?
+                }
+                None => format!("{},{}", x, y),
+            };
+            messages.push((topic.clone(), payload));
+        }
+
+        if let Some(topic) = &self.brightness_command_topic {
+            let brightness = match params.brightness {
+                Some(brightness) => Some(brightness),
+                None if on_command_type == OnCommandType::Brightness => {
+                    Some(attributes.brightness.unwrap_or(255))
+                }
+                None => None,
+            };
+            if let Some(brightness) = brightness {
+                let payload = try_render_command_template(
+                    &self.brightness_command_template,
+                    &json!(self.scale_to_device(brightness)),
+                )?;
+                messages.push((topic.clone(), payload));
+            }
+        }
+
+        if let (Some(color_temp), Some(topic)) = (params.color_temp, &self.color_temp_command_topic)
+        {
+            let payload =
+                try_render_command_template(&self.color_temp_command_template, &json!(color_temp))?;
+            messages.push((topic.clone(), payload));
+        }
+
+        if let (Some(effect), Some(topic)) = (&params.effect, &self.effect_command_topic) {
+            if self.effect_supported(effect) {
+                let payload =
+                    try_render_command_template(&self.effect_command_template, &json!(effect))?;
+                messages.push((topic.clone(), payload));
+            } else {
+                warn!("Unsupported light effect: {}", effect);
+            }
+        }
+
+        if let (Some(white), Some(topic)) = (params.white, &self.white_command_topic) {
+            let white_scale = self.white_scale.unwrap_or(DEFAULT_WHITE_SCALE);
+            let device_value = (white as f64 / 255.0 * white_scale as f64).round() as u32;
+            messages.push((topic.clone(), device_value.to_string()));
+        }
+
+        if let (OnCommandType::Last, Some(topic)) = (on_command_type, &self.command_topic) {
+            messages.push((topic.clone(), self.payload_on().to_string()));
+        }
+
+        Ok(messages)
+    }
+
+    fn json_turn_on_messages(&self, params: &LightTurnOn) -> anyhow::Result<Vec<(String, String)>> {
+        let mut message = json!({ "state": DEFAULT_PAYLOAD_ON });
+
+        if let Some(brightness) = params.brightness {
+            if self.brightness.unwrap_or(false) || self.supported_color_modes.is_some() {
+                message["brightness"] = json!(self.scale_to_device(brightness));
+            }
+        }
+        if let Some((hue, sat)) = params.hs_color {
+            message["color_mode"] = json!(ColorMode::Hs);
+            message["color"] = json!({ "h": hue, "s": sat });
+        } else if let Some((r, g, b)) = params.rgb_color {
+            message["color_mode"] = json!(ColorMode::Rgb);
+            message["color"] = json!({ "r": r, "g": g, "b": b });
+        } else if let Some((r, g, b, w)) = params.rgbw_color {
+            message["color_mode"] = json!(ColorMode::Rgbw);
+            message["color"] = json!({ "r": r, "g": g, "b": b, "w": w });
+        } else if let Some((r, g, b, c, w)) = params.rgbww_color {
+            message["color_mode"] = json!(ColorMode::Rgbww);
+            message["color"] = json!({ "r": r, "g": g, "b": b, "c": c, "w": w });
+        } else if let Some((x, y)) = params.xy_color {
+            message["color_mode"] = json!(ColorMode::Xy);
+            message["color"] = json!({ "x": x, "y": y });
+        }
+        if let Some(color_temp) = params.color_temp {
+            message["color_temp"] = json!(color_temp);
+        }
+        if let Some(effect) = &params.effect {
+            if self.effect.unwrap_or(false) && self.effect_supported(effect) {
+                message["effect"] = json!(effect);
+            }
+        }
+        if let Some(flash) = params.flash {
+            message["flash"] = json!(self.flash_time(flash));
+        }
+        if let Some(transition) = params.transition {
+            message["transition"] = json!(transition);
+        }
+
+        Ok(self
+            .command_topic
+            .iter()
+            .map(|topic| (topic.clone(), message.to_string()))
+            .collect())
+    }
+
+    fn template_turn_on_messages(
+        &self,
+        params: &LightTurnOn,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let mut variables = Map::new();
+        variables.insert("state".to_string(), json!(STATE_ON));
+        if let Some(brightness) = params.brightness {
+            variables.insert("brightness".to_string(), json!(brightness));
+        }
+        if let Some(color_temp) = params.color_temp {
+            variables.insert("color_temp".to_string(), json!(color_temp));
+        }
+        if let Some((hue, sat)) = params.hs_color {
+            variables.insert("hue".to_string(), json!(hue));
+            variables.insert("sat".to_string(), json!(sat));
+        }
+        if let Some((r, g, b)) = params.rgb_color {
+            variables.extend(rgb_variables(&[r, g, b]));
+        }
+        if let Some(effect) = &params.effect {
+            variables.insert("effect".to_string(), json!(effect));
+        }
+        if let Some(flash) = params.flash {
+            variables.insert("flash".to_string(), json!(flash));
+        }
+        if let Some(transition) = params.transition {
+            variables.insert("transition".to_string(), json!(transition));
+        }
+
+        let payload = match &self.command_on_template {
+            Some(template) => try_render_command_variables(template, &variables)?,
+            None => self.payload_on().to_string(),
+        };
+
+        Ok(self
+            .command_topic
+            .iter()
+            .map(|topic| (topic.clone(), payload.clone()))
+            .collect())
+    }
+}
+
+fn rgb_variables(values: &[u8]) -> Map<String, Value> {
+    let names: &[&str] = match values.len() {
+        5 => &["red", "green", "blue", "cold_white", "warm_white"],
+        4 => &["red", "green", "blue", "white"],
+        _ => &["red", "green", "blue"],
+    };
+    names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| (name.to_string(), json!(value)))
+        .collect()
+}
+
+/// Current light attributes, kept next to the light's [`State`].
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct LightAttributes {
+    pub brightness: Option<u8>,
+    pub color_mode: Option<ColorMode>,
+    pub color_temp: Option<u16>,
+    pub hs_color: Option<(f32, f32)>,
+    pub rgb_color: Option<(u8, u8, u8)>,
+    pub rgbw_color: Option<(u8, u8, u8, u8)>,
+    pub rgbww_color: Option<(u8, u8, u8, u8, u8)>,
+    pub xy_color: Option<(f32, f32)>,
+    pub effect: Option<String>,
+    pub effect_list: Option<Vec<String>>,
+    pub min_mireds: u16,
+    pub max_mireds: u16,
+    pub supported_color_modes: Vec<ColorMode>,
+}
+
+impl LightAttributes {
+    fn update_from_config(&mut self, config: &MqttLightConfiguration) {
+        self.effect_list = config.effect_list.clone();
+        self.min_mireds = config.min_mireds.unwrap_or(DEFAULT_MIN_MIREDS);
+        self.max_mireds = config.max_mireds.unwrap_or(DEFAULT_MAX_MIREDS);
+        self.supported_color_modes = config.supported_color_modes();
+    }
+
+    /// Apply the parameters of a turn on command, used in optimistic mode.
+    fn apply(&mut self, params: &LightTurnOn) {
+        if let Some(brightness) = params.brightness {
+            self.brightness = Some(brightness);
+        }
+        if let Some(color_temp) = params.color_temp {
+            self.color_temp = Some(color_temp);
+            self.color_mode = Some(ColorMode::ColorTemp);
+        }
+        if let Some(hs_color) = params.hs_color {
+            self.hs_color = Some(hs_color);
+            self.color_mode = Some(ColorMode::Hs);
+        }
+        if let Some(rgb_color) = params.rgb_color {
+            self.rgb_color = Some(rgb_color);
+            self.color_mode = Some(ColorMode::Rgb);
+        }
+        if let Some(rgbw_color) = params.rgbw_color {
+            self.rgbw_color = Some(rgbw_color);
+            self.color_mode = Some(ColorMode::Rgbw);
+        }
+        if let Some(rgbww_color) = params.rgbww_color {
+            self.rgbww_color = Some(rgbww_color);
+            self.color_mode = Some(ColorMode::Rgbww);
+        }
+        if let Some(xy_color) = params.xy_color {
+            self.xy_color = Some(xy_color);
+            self.color_mode = Some(ColorMode::Xy);
+        }
+        if let Some(effect) = &params.effect {
+            self.effect = Some(effect.clone());
+        }
+    }
+
+    /// Update attributes from a JSON schema state message.
+    fn update_from_json(&mut self, config: &MqttLightConfiguration, value: &Value) {
+        if let Some(brightness) = value.get("brightness").and_then(Value::as_f64) {
+            self.brightness = Some(config.scale_from_device(brightness));
+        }
+        if let Some(color_mode) = value
+            .get("color_mode")
+            .and_then(|v| serde_json::from_value::<ColorMode>(v.clone()).ok())
+        {
+            self.color_mode = Some(color_mode);
+        }
+        if let Some(color_temp) = value.get("color_temp").and_then(Value::as_u64) {
+            self.color_temp = Some(color_temp as u16);
+        }
+        if let Some(color) = value.get("color") {
+            let component = |key: &str| color.get(key).and_then(Value::as_f64);
+            if let (Some(h), Some(s)) = (component("h"), component("s")) {
+                self.hs_color = Some((h as f32, s as f32));
+            }
+            if let (Some(x), Some(y)) = (component("x"), component("y")) {
+                self.xy_color = Some((x as f32, y as f32));
+            }
+            if let (Some(r), Some(g), Some(b)) = (component("r"), component("g"), component("b")) {
+                let (r, g, b) = (r as u8, g as u8, b as u8);
+                match (component("c"), component("w")) {
+                    (Some(c), Some(w)) => self.rgbww_color = Some((r, g, b, c as u8, w as u8)),
+                    (None, Some(w)) => self.rgbw_color = Some((r, g, b, w as u8)),
+                    _ => self.rgb_color = Some((r, g, b)),
+                }
+            }
+        }
+        if let Some(effect) = value.get("effect").and_then(Value::as_str) {
+            self.effect = Some(effect.to_string());
+        }
+    }
+}
+
+/// Parameters of a [`LightCommand::TurnOn`] command, unset fields are left unchanged.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LightTurnOn {
+    pub brightness: Option<u8>,
+    /// Color temperature in mireds
+    pub color_temp: Option<u16>,
+    pub hs_color: Option<(f32, f32)>,
+    pub rgb_color: Option<(u8, u8, u8)>,
+    pub rgbw_color: Option<(u8, u8, u8, u8)>,
+    pub rgbww_color: Option<(u8, u8, u8, u8, u8)>,
+    pub xy_color: Option<(f32, f32)>,
+    pub white: Option<u8>,
+    pub effect: Option<String>,
+    /// Transition time in seconds
+    pub transition: Option<f32>,
+    pub flash: Option<LightFlash>,
+}
+
+/// Commands accepted by an MQTT light entity, triggered with the light as target.
+#[derive(Debug, Event, Clone, PartialEq)]
+pub enum LightCommand {
+    TurnOn(LightTurnOn),
+    TurnOff { transition: Option<f32> },
+    Toggle,
+}
+
+fn handle_light_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_light: Query<(&MqttLightConfiguration, &mut State, &mut LightAttributes)>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_light.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    let payload = &topic_message.event().payload;
+    let is_topic = |t: &Option<String>| t.as_deref() == Some(topic);
+
+    match config.schema() {
+        LightSchema::Basic => {
+            let render = |template: &Option<String>| {
+                try_render_template(template, payload).unwrap_or_default()
+            };
+            if is_topic(&config.state_topic) {
+                let value = render(&config.state_value_template);
+                if value == config.payload_on() {
+                    state.update(STATE_ON);
+                } else if value == config.payload_off() {
+                    state.update(STATE_OFF);
+                } else if value == PAYLOAD_NONE {
+                    state.update(STATE_UNKNOWN);
+                } else if !value.is_empty() {
+                    warn!("Ignoring light state payload {:?} on {}", value, topic);
+                }
+            }
+            if is_topic(&config.brightness_state_topic) {
+                if let Ok(value) = render(&config.brightness_value_template).parse::<f64>() {
+                    attributes.brightness = Some(config.scale_from_device(value));
+                }
+            }
+            if is_topic(&config.color_mode_state_topic) {
+                if let Ok(color_mode) = render(&config.color_mode_value_template).parse() {
+                    attributes.color_mode = Some(color_mode);
+                }
+            }
+            if is_topic(&config.color_temp_state_topic) {
+                if let Ok(color_temp) = render(&config.color_temp_value_template).parse() {
+                    attributes.color_temp = Some(color_temp);
+                }
+            }
+            if is_topic(&config.effect_state_topic) {
+                let effect = render(&config.effect_value_template);
+                if !effect.is_empty() {
+                    attributes.effect = Some(effect);
+                }
+            }
+            if is_topic(&config.hs_state_topic) {
+                if let [h, s] = parse_components::<f32>(&render(&config.hs_value_template))[..] {
+                    attributes.hs_color = Some((h, s));
+                }
+            }
+            if is_topic(&config.rgb_state_topic) {
+                if let [r, g, b] = parse_components::<u8>(&render(&config.rgb_value_template))[..] {
+                    attributes.rgb_color = Some((r, g, b));
+                }
+            }
+            if is_topic(&config.rgbw_state_topic) {
+                if let [r, g, b, w] =
+                    parse_components::<u8>(&render(&config.rgbw_value_template))[..]
+                {
+                    attributes.rgbw_color = Some((r, g, b, w));
+                }
+            }
+            if is_topic(&config.rgbww_state_topic) {
+                if let [r, g, b, c, w] =
+                    parse_components::<u8>(&render(&config.rgbww_value_template))[..]
+                {
+                    attributes.rgbww_color = Some((r, g, b, c, w));
+                }
+            }
+            if is_topic(&config.xy_state_topic) {
+                if let [x, y] = parse_components::<f32>(&render(&config.xy_value_template))[..] {
+                    attributes.xy_color = Some((x, y));
+                }
+            }
+        }
+        LightSchema::Json => {
+            if !is_topic(&config.state_topic) {
+                return;
+            }
+            let Ok(value) = serde_json::from_slice::<Value>(payload) else {
+                warn!("Invalid JSON light state on {}", topic);
+                return;
+            };
+            match value.get("state") {
+                Some(Value::String(s)) if s.eq_ignore_ascii_case(DEFAULT_PAYLOAD_ON) => {
+                    state.update(STATE_ON)
+                }
+                Some(Value::String(s)) if s.eq_ignore_ascii_case(DEFAULT_PAYLOAD_OFF) => {
+                    state.update(STATE_OFF)
+                }
+                Some(Value::Null) => state.update(STATE_UNKNOWN),
+                _ => {}
+            }
+            attributes.update_from_json(config, &value);
+        }
+        LightSchema::Template => {
+            if !is_topic(&config.state_topic) {
+                return;
+            }
+            let render = |template: &Option<String>| {
+                template
+                    .as_ref()
+                    .and_then(|_| try_render_template(template, payload).ok())
+            };
+            match render(&config.state_template).as_deref() {
+                Some(STATE_ON) => state.update(STATE_ON),
+                Some(STATE_OFF) => state.update(STATE_OFF),
+                Some(PAYLOAD_NONE) => state.update(STATE_UNKNOWN),
+                Some(value) => warn!("Ignoring light state {:?} on {}", value, topic),
+                None => {}
+            }
+            if let Some(brightness) =
+                render(&config.brightness_template).and_then(|v| v.parse().ok())
+            {
+                attributes.brightness = Some(brightness);
+            }
+            if let Some(color_temp) =
+                render(&config.color_temp_template).and_then(|v| v.parse().ok())
+            {
+                attributes.color_temp = Some(color_temp);
+            }
+            if let (Some(r), Some(g), Some(b)) = (
+                render(&config.red_template).and_then(|v| v.parse().ok()),
+                render(&config.green_template).and_then(|v| v.parse().ok()),
+                render(&config.blue_template).and_then(|v| v.parse().ok()),
+            ) {
+                attributes.rgb_color = Some((r, g, b));
+            }
+            if let Some(effect) = render(&config.effect_template) {
+                attributes.effect = Some(effect);
+            }
+        }
+    }
+}
+
+fn parse_components<T: std::str::FromStr>(value: &str) -> Vec<T> {
+    value
+        .split(',')
+        .map(|v| v.trim().parse::<T>())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_default()
+}
+
+fn on_light_command(
+    trigger: Trigger<LightCommand>,
+    publisher: MqttPublisher,
+    mut q_light: Query<(&MqttLightConfiguration, &mut State, &mut LightAttributes)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state, mut attributes)) = q_light.get_mut(entity) else {
+        return;
+    };
+
+    let command = match trigger.event() {
+        LightCommand::Toggle if state.state == STATE_ON => {
+            LightCommand::TurnOff { transition: None }
+        }
+        LightCommand::Toggle => LightCommand::TurnOn(LightTurnOn::default()),
+        command => command.clone(),
+    };
+
+    let messages = match config.command_messages(&command, &attributes) {
+        Ok(messages) => messages,
+        Err(e) => {
+            warn!("Failed to render light command: {}", e);
+            return;
+        }
+    };
+    for (topic, payload) in messages {
+        if let Err(e) = publisher.publish(entity, &topic, config.qos, config.retain, payload) {
+            warn!("Failed to publish light command: {}", e);
+            return;
+        }
+    }
+
+    if config.optimistic() {
+        match &command {
+            LightCommand::TurnOn(params) => {
+                state.update(STATE_ON);
+                attributes.apply(params);
+            }
+            LightCommand::TurnOff { .. } => state.update(STATE_OFF),
+            LightCommand::Toggle => {}
+        }
+    }
+}
+
+#[test]
+fn test_basic_light_commands() {
+    let json = r#"
+    {
+        "command_topic": "light/set",
+        "state_topic": "light/state",
+        "brightness_command_topic": "light/brightness/set",
+        "brightness_scale": 100,
+        "rgb_command_topic": "light/rgb/set",
+        "rgb_command_template": "{{ red }}-{{ green }}-{{ blue }}",
+        "on_command_type": "first"
+    }
+    "#;
+    let config: MqttLightConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.schema(), LightSchema::Basic);
+    assert_eq!(config.supported_color_modes(), vec![ColorMode::Rgb]);
+
+    let command = LightCommand::TurnOn(LightTurnOn {
+        brightness: Some(255),
+        rgb_color: Some((255, 0, 10)),
+        ..Default::default()
+    });
+    let messages = config
+        .command_messages(&command, &LightAttributes::default())
+        .unwrap();
+    assert_eq!(
+        messages,
+        vec![
+            ("light/set".to_string(), "ON".to_string()),
+            ("light/rgb/set".to_string(), "255-0-10".to_string()),
+            ("light/brightness/set".to_string(), "100".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_json_light_commands() {
+    let json = r#"
+    {
+        "schema": "json",
+        "command_topic": "zigbee2mqtt/bulb/set",
+        "state_topic": "zigbee2mqtt/bulb",
+        "brightness": true,
+        "supported_color_modes": ["xy", "color_temp"]
+    }
+    "#;
+    let config: MqttLightConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        config.supported_color_modes(),
+        vec![ColorMode::Xy, ColorMode::ColorTemp]
+    );
+
+    let command = LightCommand::TurnOn(LightTurnOn {
+        brightness: Some(128),
+        transition: Some(1.5),
+        ..Default::default()
+    });
+    let messages = config
+        .command_messages(&command, &LightAttributes::default())
+        .unwrap();
+    let payload: Value = serde_json::from_str(&messages[0].1).unwrap();
+    assert_eq!(
+        payload,
+        json!({ "state": "ON", "brightness": 128, "transition": 1.5 })
+    );
+
+    let mut attributes = LightAttributes::default();
+    attributes.update_from_json(
+        &config,
+        &json!({ "state": "ON", "brightness": 64, "color_mode": "xy", "color": { "x": 0.3, "y": 0.4 } }),
+    );
+    assert_eq!(attributes.brightness, Some(64));
+    assert_eq!(attributes.color_mode, Some(ColorMode::Xy));
+    assert_eq!(attributes.xy_color, Some((0.3, 0.4)));
+}
+
+#[test]
+fn test_template_light_commands() {
+    let json = r#"
+    {
+        "schema": "template",
+        "command_topic": "light/set",
+        "command_on_template": "on,{{ brightness | default('') }}",
+        "command_off_template": "off"
+    }
+    "#;
+    let config: MqttLightConfiguration = serde_json::from_str(json).unwrap();
+    let command = LightCommand::TurnOn(LightTurnOn {
+        brightness: Some(42),
+        ..Default::default()
+    });
+    let messages = config
+        .command_messages(&command, &LightAttributes::default())
+        .unwrap();
+    assert_eq!(messages[0].1, "on,42");
+
+    let messages = config
+        .command_messages(
+            &LightCommand::TurnOff { transition: None },
+            &LightAttributes::default(),
+        )
+        .unwrap();
+    assert_eq!(messages[0].1, "off");
+}
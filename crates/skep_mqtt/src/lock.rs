@@ -0,0 +1,274 @@
+use crate::{
+    constants::PAYLOAD_NONE,
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_variables, try_render_template, MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map};
+use skep_core::{
+    constants::{
+        STATE_JAMMED, STATE_LOCKED, STATE_LOCKING, STATE_OPEN, STATE_OPENING, STATE_UNKNOWN,
+        STATE_UNLOCKED, STATE_UNLOCKING,
+    },
+    states::State,
+};
+
+pub struct MqttLockPlugin;
+
+impl Plugin for MqttLockPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_lock_state)
+        .observe(on_lock_command);
+    }
+}
+
+const DOMAIN: &str = "lock";
+
+const DEFAULT_PAYLOAD_LOCK: &str = "LOCK";
+const DEFAULT_PAYLOAD_UNLOCK: &str = "UNLOCK";
+const DEFAULT_STATE_LOCKED: &str = "LOCKED";
+const DEFAULT_STATE_LOCKING: &str = "LOCKING";
+const DEFAULT_STATE_UNLOCKED: &str = "UNLOCKED";
+const DEFAULT_STATE_UNLOCKING: &str = "UNLOCKING";
+const DEFAULT_STATE_JAMMED: &str = "JAMMED";
+const DEFAULT_STATE_OPEN: &str = "OPEN";
+const DEFAULT_STATE_OPENING: &str = "OPENING";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttLockConfiguration>(payload.payload.clone())
+        {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid lock config {}: {}", hash, e);
+                continue;
+            }
+        };
+        if let Err(e) = config.code_regex() {
+            warn!("invalid lock code_format {}: {}", hash, e);
+            continue;
+        }
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        cmds.insert((config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttLockConfiguration {
+    pub command_topic: Option<String>,
+    pub command_template: Option<String>,
+    pub state_topic: Option<String>,
+    pub value_template: Option<String>,
+    /// Regex a code has to match before a command is sent.
+    pub code_format: Option<String>,
+    pub payload_lock: Option<String>,
+    pub payload_unlock: Option<String>,
+    /// The payload that opens the door, the open command is unsupported when not set.
+    pub payload_open: Option<String>,
+    pub state_locked: Option<String>,
+    pub state_locking: Option<String>,
+    pub state_unlocked: Option<String>,
+    pub state_unlocking: Option<String>,
+    pub state_jammed: Option<String>,
+    pub state_open: Option<String>,
+    pub state_opening: Option<String>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttLockConfiguration {
+    pub fn payload_lock(&self) -> &str {
+        self.payload_lock.as_deref().unwrap_or(DEFAULT_PAYLOAD_LOCK)
+    }
+
+    pub fn payload_unlock(&self) -> &str {
+        self.payload_unlock
+            .as_deref()
+            .unwrap_or(DEFAULT_PAYLOAD_UNLOCK)
+    }
+
+    pub fn optimistic(&self) -> bool {
+        self.optimistic.unwrap_or(false) || self.state_topic.is_none()
+    }
+
+    fn code_regex(&self) -> Result<Option<Regex>, regex::Error> {
+        self.code_format
+            .as_ref()
+            .map(|format| Regex::new(&format!("^(?:{})$", format)))
+            .transpose()
+    }
+
+    /// Check a code against `code_format`, any code is accepted when no format is configured.
+    pub fn validate_code(&self, code: Option<&str>) -> bool {
+        match self.code_regex() {
+            Ok(Some(regex)) => code.is_some_and(|code| regex.is_match(code)),
+            Ok(None) => true,
+            Err(_) => false,
+        }
+    }
+
+    /// Map a rendered state payload to one of the lock `STATE_*` values.
+    pub fn parse_state(&self, payload: &str) -> Option<&'static str> {
+        [
+            (&self.state_locked, DEFAULT_STATE_LOCKED, STATE_LOCKED),
+            (&self.state_locking, DEFAULT_STATE_LOCKING, STATE_LOCKING),
+            (&self.state_unlocked, DEFAULT_STATE_UNLOCKED, STATE_UNLOCKED),
+            (
+                &self.state_unlocking,
+                DEFAULT_STATE_UNLOCKING,
+                STATE_UNLOCKING,
+            ),
+            (&self.state_jammed, DEFAULT_STATE_JAMMED, STATE_JAMMED),
+            (&self.state_open, DEFAULT_STATE_OPEN, STATE_OPEN),
+            (&self.state_opening, DEFAULT_STATE_OPENING, STATE_OPENING),
+        ]
+        .into_iter()
+        .find(|(configured, default, _)| payload == configured.as_deref().unwrap_or(default))
+        .map(|(_, _, state)| state)
+        .or((payload == PAYLOAD_NONE).then_some(STATE_UNKNOWN))
+    }
+}
+
+/// Commands accepted by an MQTT lock entity, triggered with the lock as target.
+#[derive(Debug, Event, Clone, PartialEq, Eq)]
+pub enum LockCommand {
+    Lock { code: Option<String> },
+    Unlock { code: Option<String> },
+    Open { code: Option<String> },
+}
+
+fn handle_lock_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_lock: Query<(&MqttLockConfiguration, &mut State)>,
+) {
+    let Ok((config, mut state)) = q_lock.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic.as_deref() != Some(topic) {
+        return;
+    }
+    let value = try_render_template(&config.value_template, &topic_message.event().payload)
+        .unwrap_or_default();
+    match config.parse_state(&value) {
+        Some(new_state) => state.update(new_state),
+        None => warn!("Ignoring lock state payload {:?} on {}", value, topic),
+    }
+}
+
+fn on_lock_command(
+    trigger: Trigger<LockCommand>,
+    publisher: MqttPublisher,
+    mut q_lock: Query<(&MqttLockConfiguration, &mut State)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state)) = q_lock.get_mut(entity) else {
+        return;
+    };
+    let Some(topic) = &config.command_topic else {
+        return;
+    };
+
+    let (payload, code, optimistic_state) = match trigger.event() {
+        LockCommand::Lock { code } => (config.payload_lock(), code, STATE_LOCKED),
+        LockCommand::Unlock { code } => (config.payload_unlock(), code, STATE_UNLOCKED),
+        LockCommand::Open { code } => match &config.payload_open {
+            Some(payload) => (payload.as_str(), code, STATE_OPEN),
+            None => {
+                warn!("Lock {:?} does not support open", entity);
+                return;
+            }
+        },
+    };
+    if !config.validate_code(code.as_deref()) {
+        warn!("Invalid code for lock {:?}", entity);
+        return;
+    }
+
+    let payload = match &config.command_template {
+        Some(template) => {
+            let mut variables = Map::new();
+            variables.insert("value".to_string(), json!(payload));
+            variables.insert("code".to_string(), json!(code));
+            match try_render_command_variables(template, &variables) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    warn!("Failed to render lock command: {}", e);
+                    return;
+                }
+            }
+        }
+        None => payload.to_string(),
+    };
+
+    if let Err(e) = publisher.publish(entity, topic, config.qos, config.retain, payload) {
+        warn!("Failed to publish lock command: {}", e);
+        return;
+    }
+    if config.optimistic() {
+        state.update(optimistic_state);
+    }
+}
+
+#[test]
+fn test_lock_configuration() {
+    let json = r#"
+    {
+        "command_topic": "door/set",
+        "state_topic": "door/state",
+        "code_format": "\\d{4}",
+        "state_jammed": "MOTOR_JAMMED",
+        "payload_open": "UNLATCH"
+    }
+    "#;
+    let config: MqttLockConfiguration = serde_json::from_str(json).unwrap();
+
+    assert!(!config.optimistic());
+    assert!(config.validate_code(Some("1234")));
+    assert!(!config.validate_code(Some("12345")));
+    assert!(!config.validate_code(None));
+    assert_eq!(config.parse_state("LOCKED"), Some(STATE_LOCKED));
+    assert_eq!(config.parse_state("MOTOR_JAMMED"), Some(STATE_JAMMED));
+    assert_eq!(config.parse_state("JAMMED"), None);
+    assert_eq!(config.parse_state("None"), Some(STATE_UNKNOWN));
+
+    let config: MqttLockConfiguration = serde_json::from_str("{}").unwrap();
+    assert!(config.optimistic());
+    assert!(config.validate_code(None));
+}
@@ -0,0 +1,182 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_template, MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use skep_core::notify::{Notify, NotifyEntity};
+
+pub struct MqttNotifyPlugin;
+
+impl Plugin for MqttNotifyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(on_notify);
+    }
+}
+
+const DOMAIN: &str = "notify";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (Entity, &MQTTDiscoveryHash, &MQTTDiscoveryPayload),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttNotifyConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid notify config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        commands
+            .entity(entity)
+            .insert((config, NotifyEntity, MQTTPlatformState));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttNotifyConfiguration {
+    pub command_topic: String,
+    /// Rendered with the message as `value`
+    pub command_template: Option<String>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+/// Publish the message of a [`Notify`] to `command_topic`.
+fn on_notify(
+    trigger: Trigger<Notify>,
+    publisher: MqttPublisher,
+    q_notify: Query<&MqttNotifyConfiguration>,
+) {
+    let entity = trigger.entity();
+    let Ok(config) = q_notify.get(entity) else {
+        return;
+    };
+
+    let payload = match try_render_command_template(
+        &config.command_template,
+        &json!(trigger.event().message),
+    ) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!("Failed to render notify command: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = publisher.publish(
+        entity,
+        &config.command_topic,
+        config.qos,
+        config.retain,
+        payload,
+    ) {
+        warn!("Failed to publish notify command: {}", e);
+    }
+}
+
+#[test]
+fn test_notify_configuration() {
+    let json = r#"
+    {
+        "command_topic": "eink/kitchen/text",
+        "command_template": "{\"text\": \"{{ value }}\"}",
+        "retain": true
+    }
+    "#;
+    let config: MqttNotifyConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.retain, Some(true));
+    assert_eq!(
+        try_render_command_template(&config.command_template, &json!("Garbage day")).unwrap(),
+        r#"{"text": "Garbage day"}"#
+    );
+    assert!(serde_json::from_str::<MqttNotifyConfiguration>("{}").is_err());
+}
+
+#[test]
+fn test_notify_discovery_routing() {
+    use crate::discovery::{discover, finish_pending_discovery, setup_new_entity_from_discovery};
+    use bevy_ecs::system::RunSystemOnce;
+    use skep_core::notify::{SendNotification, SkepNotifyPlugin};
+
+    #[derive(Resource, Default)]
+    struct Received(Vec<(Entity, Notify)>);
+
+    let mut app = App::new();
+    app.add_plugins(SkepNotifyPlugin)
+        .init_resource::<Received>()
+        .add_systems(Update, create_or_update_discovery_payload)
+        .observe(setup_new_entity_from_discovery)
+        .observe(finish_pending_discovery)
+        .observe(|trigger: Trigger<Notify>, mut received: ResMut<Received>| {
+            received.0.push((trigger.entity(), trigger.event().clone()));
+        });
+    let platform = app.world_mut().spawn(SkepMqttPlatform::default()).id();
+
+    // Discovered on homeassistant/notify/kitchen/display/config
+    let hash = MQTTDiscoveryHash {
+        component: DOMAIN.to_string(),
+        discovery_id: "kitchen display".to_string(),
+    };
+    let discovery_payload = MQTTDiscoveryPayload {
+        topic: "notify/kitchen/display/config".to_string(),
+        hash: hash.clone(),
+        payload: json!({
+            "command_topic": "eink/kitchen/text",
+            "device": {"identifiers": ["kitchen_display"], "name": "Kitchen display"}
+        }),
+        platform: "mqtt".to_string(),
+    };
+    app.world_mut().run_system_once(
+        move |mut commands: Commands, mut q_platform: Query<&mut SkepMqttPlatform>| {
+            let mut mqtt_platform = q_platform.get_mut(platform).unwrap();
+            discover(
+                &mut mqtt_platform,
+                &mut commands,
+                platform,
+                discovery_payload.clone(),
+            );
+        },
+    );
+    app.update();
+
+    app.world_mut().trigger(SendNotification {
+        entity_id: "notify.kitchen_display".to_string(),
+        message: "Garbage day".to_string(),
+        title: None,
+    });
+    app.world_mut().flush();
+
+    let mut q_notify = app
+        .world_mut()
+        .query_filtered::<(Entity, &MQTTDiscoveryHash), With<NotifyEntity>>();
+    let (display, display_hash) = q_notify.single(app.world());
+    assert_eq!(display_hash, &hash);
+    let received = &app.world().resource::<Received>().0;
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].0, display);
+    assert_eq!(received[0].1.message, "Garbage day");
+}
@@ -0,0 +1,272 @@
+use crate::{
+    constants::PAYLOAD_NONE,
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_template, try_render_template, MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use skep_core::{constants::STATE_UNKNOWN, states::State};
+
+pub struct MqttNumberPlugin;
+
+impl Plugin for MqttNumberPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_number_state)
+        .observe(on_number_command);
+    }
+}
+
+const DOMAIN: &str = "number";
+
+const DEFAULT_MIN_VALUE: f64 = 1.0;
+const DEFAULT_MAX_VALUE: f64 = 100.0;
+const DEFAULT_STEP: f64 = 1.0;
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttNumberConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid number config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        if config.min() > config.max() {
+            warn!(
+                "invalid number config {}: min {} must be <= max {}",
+                hash,
+                config.min(),
+                config.max()
+            );
+            continue;
+        }
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        cmds.insert((NumberAttributes::from(&config), config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberMode {
+    #[default]
+    Auto,
+    Box,
+    Slider,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttNumberConfiguration {
+    pub command_topic: Option<String>,
+    pub command_template: Option<String>,
+    pub state_topic: Option<String>,
+    pub value_template: Option<String>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub step: Option<f64>,
+    pub mode: Option<NumberMode>,
+    pub unit_of_measurement: Option<String>,
+    pub payload_reset: Option<String>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttNumberConfiguration {
+    pub fn min(&self) -> f64 {
+        self.min.unwrap_or(DEFAULT_MIN_VALUE)
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max.unwrap_or(DEFAULT_MAX_VALUE)
+    }
+
+    pub fn step(&self) -> f64 {
+        self.step.unwrap_or(DEFAULT_STEP)
+    }
+
+    pub fn optimistic(&self) -> bool {
+        self.optimistic.unwrap_or(false) || self.state_topic.is_none()
+    }
+
+    /// Parse a rendered state payload, `Ok(None)` resets the state to unknown.
+    pub fn parse_value(&self, payload: &str) -> anyhow::Result<Option<f64>> {
+        if payload == self.payload_reset.as_deref().unwrap_or(PAYLOAD_NONE) {
+            return Ok(None);
+        }
+        let value = payload.trim().parse::<f64>()?;
+        if value < self.min() || value > self.max() {
+            anyhow::bail!(
+                "value {} is out of range {}-{}",
+                value,
+                self.min(),
+                self.max()
+            );
+        }
+        Ok(Some(value))
+    }
+
+    /// JSON value rendered into the command template, integral steps drop the fraction.
+    pub fn json_value(&self, value: f64) -> Value {
+        if self.step().fract() == 0.0 && value.fract() == 0.0 {
+            json!(value as i64)
+        } else {
+            json!(value)
+        }
+    }
+
+    /// Format a value the way it is published and stored.
+    pub fn format_value(&self, value: f64) -> String {
+        self.json_value(value).to_string()
+    }
+}
+
+/// Value constraints of a number entity.
+#[derive(Debug, Component, Clone, Serialize)]
+pub struct NumberAttributes {
+    pub min: f64,
+    pub max: f64,
+    pub step: f64,
+    pub mode: NumberMode,
+}
+
+impl From<&MqttNumberConfiguration> for NumberAttributes {
+    fn from(config: &MqttNumberConfiguration) -> Self {
+        Self {
+            min: config.min(),
+            max: config.max(),
+            step: config.step(),
+            mode: config.mode.unwrap_or_default(),
+        }
+    }
+}
+
+/// Commands accepted by an MQTT number entity, triggered with the number as target.
+#[derive(Debug, Event, Clone, Copy, PartialEq)]
+pub enum NumberCommand {
+    SetValue(f64),
+}
+
+fn handle_number_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_number: Query<(&MqttNumberConfiguration, &mut State)>,
+) {
+    let Ok((config, mut state)) = q_number.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic.as_deref() != Some(topic) {
+        return;
+    }
+    let value = try_render_template(&config.value_template, &topic_message.event().payload)
+        .unwrap_or_default();
+    match config.parse_value(&value) {
+        Ok(Some(number)) => state.update(config.format_value(number)),
+        Ok(None) => state.update(STATE_UNKNOWN),
+        Err(e) => warn!("Ignoring number payload {:?} on {}: {}", value, topic, e),
+    }
+}
+
+fn on_number_command(
+    trigger: Trigger<NumberCommand>,
+    publisher: MqttPublisher,
+    mut q_number: Query<(&MqttNumberConfiguration, &mut State)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state)) = q_number.get_mut(entity) else {
+        return;
+    };
+    let Some(topic) = &config.command_topic else {
+        return;
+    };
+    let NumberCommand::SetValue(value) = *trigger.event();
+    if value < config.min() || value > config.max() {
+        warn!(
+            "Number value {} is out of range {}-{}",
+            value,
+            config.min(),
+            config.max()
+        );
+        return;
+    }
+
+    let payload =
+        match try_render_command_template(&config.command_template, &config.json_value(value)) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to render number command: {}", e);
+                return;
+            }
+        };
+    if let Err(e) = publisher.publish(entity, topic, config.qos, config.retain, payload) {
+        warn!("Failed to publish number command: {}", e);
+        return;
+    }
+    if config.optimistic() {
+        state.update(config.format_value(value));
+    }
+}
+
+#[test]
+fn test_number_configuration() {
+    let json = r#"
+    {
+        "command_topic": "zigbee2mqtt/plug/set/countdown",
+        "command_template": "{\"countdown\": {{ value }}}",
+        "state_topic": "zigbee2mqtt/plug",
+        "value_template": "{{ value_json.countdown }}",
+        "min": 0,
+        "max": 43200,
+        "mode": "box",
+        "unit_of_measurement": "s",
+        "entity_category": "config"
+    }
+    "#;
+    let config: MqttNumberConfiguration = serde_json::from_str(json).unwrap();
+
+    assert!(!config.optimistic());
+    assert_eq!(config.mode, Some(NumberMode::Box));
+    assert_eq!(config.parse_value("120").unwrap(), Some(120.0));
+    assert_eq!(config.parse_value("None").unwrap(), None);
+    assert!(config.parse_value("50000").is_err());
+    assert!(config.parse_value("abc").is_err());
+    assert_eq!(config.format_value(120.0), "120");
+    assert_eq!(
+        try_render_command_template(&config.command_template, &config.json_value(120.0)).unwrap(),
+        r#"{"countdown": 120}"#
+    );
+}
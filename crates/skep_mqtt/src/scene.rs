@@ -0,0 +1,127 @@
+use crate::{
+    constants::DEFAULT_PAYLOAD_ON,
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use skep_core::{constants::STATE_UNKNOWN, states::State};
+
+pub struct MqttScenePlugin;
+
+impl Plugin for MqttScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(on_scene_command);
+    }
+}
+
+const DOMAIN: &str = "scene";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttSceneConfiguration>(payload.payload.clone())
+        {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid scene config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        cmds.insert((config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttSceneConfiguration {
+    pub command_topic: String,
+    pub payload_on: Option<String>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttSceneConfiguration {
+    pub fn payload_on(&self) -> &str {
+        self.payload_on.as_deref().unwrap_or(DEFAULT_PAYLOAD_ON)
+    }
+}
+
+/// Commands accepted by an MQTT scene entity, triggered with the scene as target.
+#[derive(Debug, Event, Clone, Copy, PartialEq, Eq)]
+pub enum SceneCommand {
+    Activate,
+}
+
+/// Publish `payload_on`, the state of a scene is the time it was last activated.
+fn on_scene_command(
+    trigger: Trigger<SceneCommand>,
+    publisher: MqttPublisher,
+    mut q_scene: Query<(&MqttSceneConfiguration, &mut State)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state)) = q_scene.get_mut(entity) else {
+        return;
+    };
+    let SceneCommand::Activate = trigger.event();
+
+    if let Err(e) = publisher.publish(
+        entity,
+        &config.command_topic,
+        config.qos,
+        config.retain,
+        config.payload_on(),
+    ) {
+        warn!("Failed to publish scene command: {}", e);
+        return;
+    }
+    state.update(Utc::now().to_rfc3339());
+}
+
+#[test]
+fn test_scene_configuration() {
+    let json = r#"
+    {
+        "command_topic": "living_room/scene/set",
+        "payload_on": "movie_night"
+    }
+    "#;
+    let config: MqttSceneConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.payload_on(), "movie_night");
+
+    let config: MqttSceneConfiguration =
+        serde_json::from_str(r#"{"command_topic": "living_room/scene/set"}"#).unwrap();
+    assert_eq!(config.payload_on(), "ON");
+}
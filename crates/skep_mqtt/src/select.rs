@@ -0,0 +1,190 @@
+use crate::{
+    constants::PAYLOAD_NONE,
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_template, try_render_template, MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use skep_core::{constants::STATE_UNKNOWN, states::State};
+
+pub struct MqttSelectPlugin;
+
+impl Plugin for MqttSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_select_state)
+        .observe(on_select_command);
+    }
+}
+
+const DOMAIN: &str = "select";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttSelectConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid select config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        cmds.insert((
+            SelectAttributes {
+                options: config.options.clone(),
+            },
+            config,
+            MQTTPlatformState,
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttSelectConfiguration {
+    pub command_topic: Option<String>,
+    pub command_template: Option<String>,
+    pub state_topic: Option<String>,
+    pub value_template: Option<String>,
+    pub options: Vec<String>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttSelectConfiguration {
+    pub fn optimistic(&self) -> bool {
+        self.optimistic.unwrap_or(false) || self.state_topic.is_none()
+    }
+
+    /// Map a rendered state payload to one of the configured options.
+    pub fn parse_option<'a>(&'a self, payload: &str) -> Option<&'a str> {
+        if payload == PAYLOAD_NONE {
+            return Some(STATE_UNKNOWN);
+        }
+        self.options
+            .iter()
+            .find(|option| *option == payload)
+            .map(String::as_str)
+    }
+}
+
+/// Options a select entity can be set to.
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct SelectAttributes {
+    pub options: Vec<String>,
+}
+
+/// Commands accepted by an MQTT select entity, triggered with the select as target.
+#[derive(Debug, Event, Clone, PartialEq, Eq)]
+pub enum SelectCommand {
+    SelectOption(String),
+}
+
+fn handle_select_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_select: Query<(&MqttSelectConfiguration, &mut State)>,
+) {
+    let Ok((config, mut state)) = q_select.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic.as_deref() != Some(topic) {
+        return;
+    }
+    let value = try_render_template(&config.value_template, &topic_message.event().payload)
+        .unwrap_or_default();
+    match config.parse_option(&value) {
+        Some(option) => state.update(option),
+        None => warn!(
+            "Ignoring select payload {:?} on {}, expected one of {:?}",
+            value, topic, config.options
+        ),
+    }
+}
+
+fn on_select_command(
+    trigger: Trigger<SelectCommand>,
+    publisher: MqttPublisher,
+    mut q_select: Query<(&MqttSelectConfiguration, &mut State)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state)) = q_select.get_mut(entity) else {
+        return;
+    };
+    let Some(topic) = &config.command_topic else {
+        return;
+    };
+    let SelectCommand::SelectOption(option) = trigger.event();
+    if !config.options.contains(option) {
+        warn!("Unsupported select option: {}", option);
+        return;
+    }
+
+    let payload = match try_render_command_template(&config.command_template, &json!(option)) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!("Failed to render select command: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = publisher.publish(entity, topic, config.qos, config.retain, payload) {
+        warn!("Failed to publish select command: {}", e);
+        return;
+    }
+    if config.optimistic() {
+        state.update(option);
+    }
+}
+
+#[test]
+fn test_select_configuration() {
+    let json = r#"
+    {
+        "command_topic": "zigbee2mqtt/plug/set/power_outage_memory",
+        "state_topic": "zigbee2mqtt/plug",
+        "value_template": "{{ value_json.power_outage_memory }}",
+        "options": ["on", "off", "restore"]
+    }
+    "#;
+    let config: MqttSelectConfiguration = serde_json::from_str(json).unwrap();
+
+    assert!(!config.optimistic());
+    assert_eq!(config.parse_option("restore"), Some("restore"));
+    assert_eq!(config.parse_option("None"), Some(STATE_UNKNOWN));
+    assert_eq!(config.parse_option("toggle"), None);
+    assert!(serde_json::from_str::<MqttSelectConfiguration>("{}").is_err());
+}
@@ -1,11 +1,23 @@
-use crate::SkepMqttPlatform;
+use crate::{
+    constants::PAYLOAD_NONE,
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{delayed_state, try_render_template, MQTTAttributesBlocked, MQTTPlatformState},
+    SkepMqttPlatform,
+};
 use bevy_app::{App, Plugin, Update};
 use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use chrono::{DateTime, Utc};
 use serde_json::Value;
-use skep_core::constants::EntityCategory;
-use skep_sensor::SensorDeviceClass;
+use skep_core::{
+    constants::{EntityCategory, STATE_UNAVAILABLE, STATE_UNKNOWN},
+    helper::event::DelayedActions,
+    states::State,
+    typing::SetupConfigEntry,
+};
+use skep_sensor::{Sensor, SensorDeviceClass, SensorStateClass};
 
-use crate::discovery::MQTTDiscoveryNew;
 use bevy_ecs::{
     prelude::{Added, Commands},
     system::Query,
@@ -28,7 +40,8 @@ pub struct MqttSensorPlugin;
 impl Plugin for MqttSensorPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, on_mqtt_platform_added)
-            .add_systems(Update, create_by_discovery_payload);
+            .add_systems(Update, create_or_update_discovery_payload)
+            .observe(handle_sensor_state);
     }
 }
 
@@ -47,15 +60,76 @@ fn on_mqtt_platform_added(
     }
 }
 
-fn create_by_discovery_payload(mut create_ev: EventReader<MQTTDiscoveryNew>) {
-    for ev in create_ev.read() {
-        if ev.hash.component == DOMAIN {
-            println!("mqtt sensor Create by discovery payload: {:#?}", ev);
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    mut q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&mut Sensor>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state, opt_sensor) in q_discovery.iter_mut() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttSensorConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid sensor config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        let sensor = match Sensor::from_config(SetupConfigEntry {
+            component: DOMAIN.to_string(),
+            object_id: hash.discovery_id.clone(),
+            payload: payload.payload.clone(),
+        }) {
+            Ok(sensor) => sensor,
+            Err(e) => {
+                warn!("invalid sensor config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        match opt_sensor {
+            // Keep the last value and reset across discovery updates
+            Some(mut current) => {
+                *current = Sensor {
+                    native_value: current.native_value.take(),
+                    last_reset: current.last_reset,
+                    ..sensor
+                };
+            }
+            None => {
+                cmds.insert(sensor);
+            }
         }
+        if opt_state.is_none() {
+            // An expiring sensor is unavailable until its first state arrives
+            let state = if config.expire_after().is_some() {
+                STATE_UNAVAILABLE
+            } else {
+                STATE_UNKNOWN
+            };
+            cmds.insert(State::new(state.to_string()));
+        }
+        cmds.insert((
+            config,
+            MQTTPlatformState,
+            MQTTAttributesBlocked(&MQTT_SENSOR_ATTRIBUTES_BLOCKED),
+        ));
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
 pub struct MqttSensorConfiguration {
     pub availability_topic: Option<String>,
     // pub device: Option<DeviceInfo>,
@@ -65,19 +139,22 @@ pub struct MqttSensorConfiguration {
     /// The category of the entity. When set, the entity category must be diagnostic for sensors.
     pub entity_category: Option<EntityCategory>,
     /// If set, it defines the number of seconds after the sensor’s state expires, if it’s not
-    /// updated. After expiry, the sensor’s state becomes unavailable. Default the sensors state
-    /// never expires.
-    pub expire_after: Option<i32>,
+    /// updated. After expiry, the sensor’s state becomes unavailable. By default, or with `0`, the
+    /// sensors state never expires.
+    pub expire_after: Option<u32>,
     pub force_update: Option<bool>,
     pub icon: Option<String>,
     pub json_attributes_template: Option<String>,
     pub json_attributes_topic: Option<String>,
+    /// Extracts the `last_reset` timestamp from the state payload, only used for the `total`
+    /// state class.
     pub last_reset_value_template: Option<String>,
     pub name: Option<String>,
     pub object_id: Option<String>,
     pub options: Option<Value>,
     pub payload_available: Option<String>,
     pub payload_not_available: Option<String>,
+    pub state_class: Option<SensorStateClass>,
     pub suggested_display_precision: Option<i32>,
     pub state_topic: String,
     pub unique_id: Option<String>,
@@ -85,6 +162,79 @@ pub struct MqttSensorConfiguration {
     pub value_template: Option<String>,
 }
 
+impl MqttSensorConfiguration {
+    pub fn force_update(&self) -> bool {
+        self.force_update.unwrap_or(false)
+    }
+
+    /// Seconds after which the state expires, `None` when it never expires.
+    pub fn expire_after(&self) -> Option<u32> {
+        self.expire_after.filter(|secs| *secs > 0)
+    }
+
+    /// Render the `last_reset` of a state payload, `Ok(None)` when it was reset to unknown.
+    pub fn parse_last_reset(&self, payload: &[u8]) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let value = try_render_template(&self.last_reset_value_template, payload)?;
+        let value = value.trim();
+        if value.is_empty() || value == PAYLOAD_NONE {
+            return Ok(None);
+        }
+        Ok(Some(
+            DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc),
+        ))
+    }
+}
+
+fn handle_sensor_state(
+    topic_message: Trigger<TopicMessage>,
+    mut commands: Commands,
+    mut q_sensor: Query<(&MqttSensorConfiguration, &mut Sensor, &mut State)>,
+) {
+    let entity = topic_message.entity();
+    let Ok((config, mut sensor, mut state)) = q_sensor.get_mut(entity) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic != topic {
+        return;
+    }
+    let payload = &topic_message.event().payload[..];
+
+    if let Some(expire_after) = config.expire_after() {
+        commands.entity(entity).insert(DelayedActions(
+            delayed_state(entity, expire_after as f32, STATE_UNAVAILABLE)
+                .into_iter()
+                .collect(),
+        ));
+    }
+
+    if config.last_reset_value_template.is_some() {
+        if sensor.state_class == Some(SensorStateClass::Total) {
+            match config.parse_last_reset(payload) {
+                Ok(last_reset) => sensor.last_reset = last_reset,
+                Err(e) => warn!("Ignoring sensor last_reset on {}: {}", topic, e),
+            }
+        } else {
+            warn!(
+                "Ignoring sensor last_reset on {}, the state class is not total",
+                topic
+            );
+        }
+    }
+
+    let value = try_render_template(&config.value_template, payload).unwrap_or_default();
+    match sensor.parse_native_value(&value) {
+        Ok(native_value) => {
+            sensor.native_value = native_value;
+            let new_state = sensor.state();
+            if config.force_update() || state.state != new_state {
+                state.update(new_state);
+            }
+        }
+        Err(e) => warn!("Ignoring sensor value {:?} on {}: {}", value, topic, e),
+    }
+}
+
 #[test]
 fn test_mqtt_configuration() {
     let json = r#"
@@ -115,4 +265,46 @@ fn test_mqtt_configuration() {
     let sensor: MqttSensorConfiguration = serde_json::from_str(json).unwrap();
 
     println!("{:#?}", sensor);
+    assert_eq!(sensor.expire_after(), None);
+
+    let config = |expire_after: i64| {
+        serde_json::from_value::<MqttSensorConfiguration>(serde_json::json!({
+            "state_topic": "watermeter/value",
+            "expire_after": expire_after
+        }))
+    };
+    assert_eq!(config(300).unwrap().expire_after(), Some(300));
+    assert_eq!(config(0).unwrap().expire_after(), None);
+    assert!(config(-1).is_err());
+}
+
+#[test]
+fn test_last_reset() {
+    let json = r#"
+    {
+        "state_topic": "meter/energy",
+        "value_template": "{{ value_json.total }}",
+        "last_reset_value_template": "{{ value_json.since }}",
+        "device_class": "energy",
+        "state_class": "total",
+        "unit_of_measurement": "kWh"
+    }
+    "#;
+    let config: MqttSensorConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.state_class, Some(SensorStateClass::Total));
+
+    let payload = br#"{"total": 12.5, "since": "2024-09-01T00:00:00+00:00"}"#;
+    assert_eq!(
+        config.parse_last_reset(payload).unwrap(),
+        Some("2024-09-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap())
+    );
+    assert_eq!(
+        config
+            .parse_last_reset(br#"{"total": 12.5, "since": "None"}"#)
+            .unwrap(),
+        None
+    );
+    assert!(config
+        .parse_last_reset(br#"{"total": 12.5, "since": "yesterday"}"#)
+        .is_err());
 }
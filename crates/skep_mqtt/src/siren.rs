@@ -0,0 +1,338 @@
+use crate::{
+    constants::{DEFAULT_PAYLOAD_OFF, DEFAULT_PAYLOAD_ON, PAYLOAD_NONE},
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_variables, try_render_template, MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use skep_core::{
+    constants::{STATE_OFF, STATE_ON, STATE_UNKNOWN},
+    states::State,
+};
+
+pub struct MqttSirenPlugin;
+
+impl Plugin for MqttSirenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_siren_state)
+        .observe(on_siren_command);
+    }
+}
+
+const DOMAIN: &str = "siren";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    mut q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&mut SirenAttributes>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state, opt_attributes) in q_discovery.iter_mut() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttSirenConfiguration>(payload.payload.clone())
+        {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid siren config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        match opt_attributes {
+            Some(mut attributes) => attributes.update_from_config(&config),
+            None => {
+                let mut attributes = SirenAttributes::default();
+                attributes.update_from_config(&config);
+                cmds.insert(attributes);
+            }
+        }
+        cmds.insert((config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttSirenConfiguration {
+    pub command_topic: Option<String>,
+    /// Template for the turn on payload, rendered with `value`, `tone`, `volume_level` and
+    /// `duration`.
+    pub command_template: Option<String>,
+    /// Template for the turn off payload, `command_template` is used when not set.
+    pub command_off_template: Option<String>,
+    pub state_topic: Option<String>,
+    pub state_value_template: Option<String>,
+    pub payload_on: Option<String>,
+    pub payload_off: Option<String>,
+    pub state_on: Option<String>,
+    pub state_off: Option<String>,
+    #[serde(default)]
+    pub available_tones: Vec<String>,
+    #[serde(default = "default_true")]
+    pub support_duration: bool,
+    #[serde(default = "default_true")]
+    pub support_volume_set: bool,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttSirenConfiguration {
+    pub fn payload_on(&self) -> &str {
+        self.payload_on.as_deref().unwrap_or(DEFAULT_PAYLOAD_ON)
+    }
+
+    pub fn payload_off(&self) -> &str {
+        self.payload_off.as_deref().unwrap_or(DEFAULT_PAYLOAD_OFF)
+    }
+
+    pub fn state_on(&self) -> &str {
+        self.state_on.as_deref().unwrap_or(self.payload_on())
+    }
+
+    pub fn state_off(&self) -> &str {
+        self.state_off.as_deref().unwrap_or(self.payload_off())
+    }
+
+    pub fn optimistic(&self) -> bool {
+        self.optimistic.unwrap_or(false) || self.state_topic.is_none()
+    }
+
+    /// Map a rendered on/off payload to [`STATE_ON`] or [`STATE_OFF`].
+    pub fn parse_state(&self, payload: &str) -> Option<&'static str> {
+        if payload == self.state_on() {
+            Some(STATE_ON)
+        } else if payload == self.state_off() {
+            Some(STATE_OFF)
+        } else if payload == PAYLOAD_NONE {
+            Some(STATE_UNKNOWN)
+        } else {
+            None
+        }
+    }
+}
+
+/// Current siren parameters next to the on/off [`State`].
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct SirenAttributes {
+    pub tone: Option<String>,
+    pub volume_level: Option<f32>,
+    pub duration: Option<u32>,
+    pub available_tones: Vec<String>,
+    pub support_duration: bool,
+    pub support_volume_set: bool,
+}
+
+impl SirenAttributes {
+    fn update_from_config(&mut self, config: &MqttSirenConfiguration) {
+        self.available_tones = config.available_tones.clone();
+        self.support_duration = config.support_duration;
+        self.support_volume_set = config.support_volume_set;
+    }
+
+    /// Update from a JSON state payload, e.g. `{"state": "ON", "tone": "bell"}`.
+    fn update_from_json(&mut self, object: &Map<String, Value>) {
+        if let Some(tone) = object.get("tone") {
+            self.tone = tone.as_str().map(str::to_string);
+        }
+        if let Some(volume_level) = object.get("volume_level") {
+            self.volume_level = volume_level.as_f64().map(|v| v as f32);
+        }
+        if let Some(duration) = object.get("duration") {
+            self.duration = duration.as_u64().map(|v| v as u32);
+        }
+    }
+}
+
+/// Parameters for turning a siren on, unsupported parameters are dropped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SirenTurnOn {
+    pub tone: Option<String>,
+    /// Volume between 0 and 1
+    pub volume_level: Option<f32>,
+    /// Duration in seconds
+    pub duration: Option<u32>,
+}
+
+/// Commands accepted by an MQTT siren entity, triggered with the siren as target.
+#[derive(Debug, Event, Clone, PartialEq)]
+pub enum SirenCommand {
+    TurnOn(SirenTurnOn),
+    TurnOff,
+    Toggle,
+}
+
+fn handle_siren_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_siren: Query<(&MqttSirenConfiguration, &mut State, &mut SirenAttributes)>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_siren.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic.as_deref() != Some(topic) {
+        return;
+    }
+    let value = try_render_template(&config.state_value_template, &topic_message.event().payload)
+        .unwrap_or_default();
+
+    if let Some(new_state) = config.parse_state(&value) {
+        state.update(new_state);
+        return;
+    }
+    // A JSON payload carries the state and the current siren parameters
+    let Ok(Value::Object(object)) = serde_json::from_str::<Value>(&value) else {
+        warn!("Ignoring siren state payload {:?} on {}", value, topic);
+        return;
+    };
+    if let Some(payload) = object.get("state").and_then(Value::as_str) {
+        match config.parse_state(payload) {
+            Some(new_state) => state.update(new_state),
+            None => warn!("Ignoring siren state {:?} on {}", payload, topic),
+        }
+    }
+    attributes.update_from_json(&object);
+}
+
+fn on_siren_command(
+    trigger: Trigger<SirenCommand>,
+    publisher: MqttPublisher,
+    mut q_siren: Query<(&MqttSirenConfiguration, &mut State, &mut SirenAttributes)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state, mut attributes)) = q_siren.get_mut(entity) else {
+        return;
+    };
+    let Some(topic) = &config.command_topic else {
+        return;
+    };
+
+    // `None` turns the siren off
+    let turn_on = match trigger.event() {
+        SirenCommand::TurnOn(turn_on) => Some(turn_on.clone()),
+        SirenCommand::TurnOff => None,
+        SirenCommand::Toggle if state.state == STATE_ON => None,
+        SirenCommand::Toggle => Some(SirenTurnOn::default()),
+    };
+
+    let mut variables = Map::new();
+    let (template, new_state) = match &turn_on {
+        Some(turn_on) => {
+            variables.insert("value".to_string(), json!(config.payload_on()));
+            if let Some(tone) = &turn_on.tone {
+                if config.available_tones.contains(tone) {
+                    variables.insert("tone".to_string(), json!(tone));
+                } else {
+                    warn!("Unsupported siren tone: {}", tone);
+                }
+            }
+            if config.support_volume_set {
+                if let Some(volume_level) = turn_on.volume_level {
+                    variables.insert(
+                        "volume_level".to_string(),
+                        json!(volume_level.clamp(0.0, 1.0)),
+                    );
+                }
+            }
+            if config.support_duration {
+                if let Some(duration) = turn_on.duration {
+                    variables.insert("duration".to_string(), json!(duration));
+                }
+            }
+            (config.command_template.as_ref(), STATE_ON)
+        }
+        None => {
+            variables.insert("value".to_string(), json!(config.payload_off()));
+            (
+                config
+                    .command_off_template
+                    .as_ref()
+                    .or(config.command_template.as_ref()),
+                STATE_OFF,
+            )
+        }
+    };
+
+    let payload = match template {
+        Some(template) => match try_render_command_variables(template, &variables) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to render siren command: {}", e);
+                return;
+            }
+        },
+        None => variables["value"].as_str().unwrap_or_default().to_string(),
+    };
+    if let Err(e) = publisher.publish(entity, topic, config.qos, config.retain, payload) {
+        warn!("Failed to publish siren command: {}", e);
+        return;
+    }
+
+    if config.optimistic() {
+        state.update(new_state);
+        attributes.update_from_json(&variables);
+    }
+}
+
+#[test]
+fn test_siren_configuration() {
+    let json = r#"
+    {
+        "command_topic": "siren/set",
+        "command_template": "{\"state\": \"{{ value }}\", \"tone\": \"{{ tone }}\"}",
+        "state_topic": "siren/state",
+        "state_on": "active",
+        "available_tones": ["bell", "alarm"],
+        "support_duration": false
+    }
+    "#;
+    let config: MqttSirenConfiguration = serde_json::from_str(json).unwrap();
+
+    assert!(!config.optimistic());
+    assert!(!config.support_duration);
+    assert!(config.support_volume_set);
+    assert_eq!(config.parse_state("active"), Some(STATE_ON));
+    assert_eq!(config.parse_state("OFF"), Some(STATE_OFF));
+    assert_eq!(config.parse_state("ON"), None);
+
+    let mut attributes = SirenAttributes::default();
+    let object = serde_json::from_str::<Map<String, Value>>(
+        r#"{"state": "active", "tone": "bell", "volume_level": 0.5}"#,
+    )
+    .unwrap();
+    attributes.update_from_json(&object);
+    assert_eq!(attributes.tone.as_deref(), Some("bell"));
+    assert_eq!(attributes.volume_level, Some(0.5));
+    assert_eq!(attributes.duration, None);
+}
@@ -0,0 +1,145 @@
+use crate::constants::{DEFAULT_PAYLOAD_OFFLINE, DEFAULT_PAYLOAD_ONLINE};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::{
+    rumqttc::{self, LastWill, QoS},
+    MqttClient, MqttClientConnected,
+};
+use serde::Deserialize;
+
+/// A configured birth or will message, an empty `{}` disables it. A missing topic defaults to
+/// `<discovery_prefix>/status`, a missing payload to `online`/`offline`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct MqttMessageConfig {
+    pub topic: Option<String>,
+    pub payload: Option<String>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: String,
+    pub qos: QoS,
+    pub retain: bool,
+}
+
+impl MqttMessage {
+    /// Resolve a configured message, defaulting to `payload` on `<prefix>/status`. `None` when
+    /// it is disabled with an empty `{}`.
+    pub fn from_config(
+        config: Option<MqttMessageConfig>,
+        discovery_prefix: &str,
+        payload: &str,
+    ) -> Option<Self> {
+        let config = match config {
+            Some(MqttMessageConfig {
+                topic: None,
+                payload: None,
+                qos: None,
+                retain: None,
+            }) => return None,
+            Some(config) => config,
+            None => MqttMessageConfig::default(),
+        };
+
+        Some(Self {
+            topic: config
+                .topic
+                .filter(|topic| !topic.is_empty())
+                .unwrap_or_else(|| format!("{}/status", discovery_prefix)),
+            payload: config.payload.unwrap_or_else(|| payload.to_string()),
+            qos: rumqttc::qos(config.qos.unwrap_or(0)).unwrap_or(QoS::AtMostOnce),
+            retain: config.retain.unwrap_or(false),
+        })
+    }
+
+    pub fn last_will(&self) -> LastWill {
+        LastWill::new(&self.topic, self.payload.as_str(), self.qos, self.retain)
+    }
+
+    fn publish(&self, client: &MqttClient) {
+        if let Err(e) = client.publish(&self.topic, self.qos, self.retain, self.payload.as_str()) {
+            warn!("Failed to publish {}: {}", self.topic, e);
+        } else {
+            debug!("publish {} to {}", self.payload, self.topic);
+        }
+    }
+}
+
+/// Birth and will message of an MQTT platform.
+#[derive(Debug, Component, Clone, Default)]
+pub struct MqttStatusMessages {
+    pub birth: Option<MqttMessage>,
+    pub will: Option<MqttMessage>,
+}
+
+impl MqttStatusMessages {
+    pub fn from_config(
+        birth_message: Option<MqttMessageConfig>,
+        will_message: Option<MqttMessageConfig>,
+        discovery_prefix: &str,
+    ) -> Self {
+        Self {
+            birth: MqttMessage::from_config(
+                birth_message,
+                discovery_prefix,
+                DEFAULT_PAYLOAD_ONLINE,
+            ),
+            will: MqttMessage::from_config(will_message, discovery_prefix, DEFAULT_PAYLOAD_OFFLINE),
+        }
+    }
+}
+
+/// Publish the birth message on every (re)connect, after the discovery topics are subscribed, so
+/// devices that listen on `<prefix>/status` re-announce their configs to us.
+pub(crate) fn publish_birth_message(
+    q_clients: Query<(&MqttClient, &MqttStatusMessages), Added<MqttClientConnected>>,
+) {
+    for (client, messages) in q_clients.iter() {
+        if let Some(birth) = &messages.birth {
+            birth.publish(client);
+        }
+    }
+}
+
+#[test]
+fn test_status_messages() {
+    let messages = MqttStatusMessages::from_config(None, None, "homeassistant");
+    let birth = messages.birth.unwrap();
+    assert_eq!(birth.topic, "homeassistant/status");
+    assert_eq!(birth.payload, "online");
+    assert_eq!(messages.will.unwrap().payload, "offline");
+
+    let birth_message: MqttMessageConfig = serde_json::from_str(
+        r#"{"topic": "skep/status", "payload": "up", "qos": 1, "retain": true}"#,
+    )
+    .unwrap();
+    let messages = MqttStatusMessages::from_config(
+        Some(birth_message),
+        Some(MqttMessageConfig::default()),
+        "homeassistant",
+    );
+    assert_eq!(
+        messages.birth,
+        Some(MqttMessage {
+            topic: "skep/status".to_string(),
+            payload: "up".to_string(),
+            qos: QoS::AtLeastOnce,
+            retain: true,
+        })
+    );
+    assert!(messages.will.is_none());
+
+    let birth_message: MqttMessageConfig = serde_json::from_str(r#"{"payload": "up"}"#).unwrap();
+    let will_message: MqttMessageConfig = serde_json::from_str(r#"{"topic": ""}"#).unwrap();
+    let messages =
+        MqttStatusMessages::from_config(Some(birth_message), Some(will_message), "homeassistant");
+    let birth = messages.birth.unwrap();
+    assert_eq!(birth.topic, "homeassistant/status");
+    assert_eq!(birth.payload, "up");
+    let will = messages.will.unwrap();
+    assert_eq!(will.topic, "homeassistant/status");
+    assert_eq!(will.payload, "offline");
+}
@@ -1,7 +1,8 @@
 use crate::{
     discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
     entity::{
-        handle_available_value, handle_state_value, MQTTAvailability, MQTTAvailabilityConfiguration,
+        handle_available_value, handle_state_value, MQTTAvailability,
+        MQTTAvailabilityConfiguration, MQTTJsonAttributesConfiguration,
     },
 };
 use bevy_app::Update;
@@ -10,7 +11,7 @@ use bevy_ecs::{
     component::Component,
     entity::Entity,
     observer::Trigger,
-    prelude::{Added, Changed, Commands, Event, Query},
+    prelude::{Added, Changed, Commands, Event, Query, With},
 };
 use bevy_hierarchy::{BuildChildren, Children, DespawnRecursiveExt, HierarchyQueryExt};
 use bevy_log::debug;
@@ -32,6 +33,86 @@ pub struct MQTTStateSubscription {
     pub qos: Option<u8>, // default 0
 }
 
+/// Marker for [`SubscribeTopic`] children spawned for the extra state topics of a platform.
+#[derive(Debug, Component, Default, Reflect)]
+pub struct MQTTPlatformTopic;
+
+/// Replace the platform topic subscriptions of `entity` with `topics`, keeping the ones that are
+/// already subscribed.
+pub(crate) fn update_platform_subscriptions<'a>(
+    commands: &mut Commands,
+    entity: Entity,
+    opt_children: Option<&Children>,
+    q_platform_topic: &Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+    topics: impl IntoIterator<Item = &'a str>,
+    qos: u8,
+) {
+    let mut topics: HashSet<&str> = topics.into_iter().filter(|t| !t.is_empty()).collect();
+    if let Some(children) = opt_children {
+        for child in children.iter() {
+            if let Ok(sub_topic) = q_platform_topic.get(*child) {
+                if !topics.remove(sub_topic.topic()) {
+                    commands.entity(*child).despawn_recursive();
+                }
+            }
+        }
+    }
+
+    for topic in topics {
+        let child_id = commands
+            .spawn((SubscribeTopic::new(topic, qos), MQTTPlatformTopic))
+            .id();
+        commands.entity(entity).add_child(child_id);
+    }
+}
+
+/// Marker for the [`SubscribeTopic`] child spawned for the `json_attributes_topic` of an entity.
+#[derive(Debug, Component, Default, Reflect)]
+pub struct MQTTJsonAttributesTopic;
+
+/// Subscribe to the `json_attributes_topic` of an entity, unless it is already the state topic.
+pub(crate) fn update_json_attributes_subscription(
+    mut commands: Commands,
+    q_attributes_topic: Query<&SubscribeTopic, With<MQTTJsonAttributesTopic>>,
+    q_attributes: Query<
+        (
+            Entity,
+            &MQTTJsonAttributesConfiguration,
+            Option<&MQTTStateSubscription>,
+            Option<&Children>,
+        ),
+        Changed<MQTTJsonAttributesConfiguration>,
+    >,
+) {
+    for (entity, config, opt_state_sub, opt_children) in q_attributes.iter() {
+        let state_topic = opt_state_sub.map(|sub| sub.state_topic.as_str());
+        let mut topic = config
+            .json_attributes_topic
+            .as_deref()
+            .filter(|topic| !topic.is_empty() && Some(*topic) != state_topic);
+
+        if let Some(children) = opt_children {
+            for child in children.iter() {
+                if let Ok(sub_topic) = q_attributes_topic.get(*child) {
+                    if topic == Some(sub_topic.topic()) {
+                        topic = None;
+                    } else {
+                        commands.entity(*child).despawn_recursive();
+                    }
+                }
+            }
+        }
+
+        if let Some(topic) = topic {
+            let qos = opt_state_sub.and_then(|sub| sub.qos).unwrap_or(0);
+            let child_id = commands
+                .spawn((SubscribeTopic::new(topic, qos), MQTTJsonAttributesTopic))
+                .id();
+            commands.entity(entity).add_child(child_id);
+        }
+    }
+}
+
 pub(crate) fn add_state_subscription(
     mut commands: Commands,
     mut q_discovery: Query<
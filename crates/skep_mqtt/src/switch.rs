@@ -0,0 +1,207 @@
+use crate::{
+    constants::{DEFAULT_PAYLOAD_OFF, DEFAULT_PAYLOAD_ON, PAYLOAD_NONE},
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_template, MQTTPlatformState, MqttPublisher},
+    subscription::MQTTStateSubscription,
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use serde::{Deserialize, Serialize};
+use skep_core::{
+    constants::{STATE_OFF, STATE_ON, STATE_UNKNOWN},
+    states::State,
+};
+
+pub struct MqttSwitchPlugin;
+
+impl Plugin for MqttSwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_switch_state)
+        .observe(on_switch_command);
+    }
+}
+
+const DOMAIN: &str = "switch";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        match serde_json::from_value::<MqttSwitchConfiguration>(payload.payload.clone()) {
+            Ok(config) => {
+                debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+                let mut cmds = commands.entity(entity);
+                cmds.insert((config, MQTTPlatformState));
+                if opt_state.is_none() {
+                    cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+                }
+            }
+            Err(e) => warn!("invalid switch config {}: {}", hash, e),
+        }
+    }
+}
+
+/// Commands accepted by an MQTT switch entity, triggered with the switch as target.
+#[derive(Debug, Event, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchCommand {
+    TurnOn,
+    TurnOff,
+    Toggle,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttSwitchConfiguration {
+    pub command_topic: Option<String>,
+    pub state_topic: Option<String>,
+    pub payload_on: Option<String>,
+    pub payload_off: Option<String>,
+    /// The payload that represents the on state, defaults to `payload_on`.
+    pub state_on: Option<String>,
+    /// The payload that represents the off state, defaults to `payload_off`.
+    pub state_off: Option<String>,
+    /// Flag that defines if switch works in optimistic mode. Defaults to `true` if no
+    /// `state_topic` is defined.
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+    pub value_template: Option<String>,
+}
+
+impl MqttSwitchConfiguration {
+    pub fn payload_on(&self) -> &str {
+        self.payload_on.as_deref().unwrap_or(DEFAULT_PAYLOAD_ON)
+    }
+
+    pub fn payload_off(&self) -> &str {
+        self.payload_off.as_deref().unwrap_or(DEFAULT_PAYLOAD_OFF)
+    }
+
+    pub fn state_on(&self) -> &str {
+        self.state_on.as_deref().unwrap_or(self.payload_on())
+    }
+
+    pub fn state_off(&self) -> &str {
+        self.state_off.as_deref().unwrap_or(self.payload_off())
+    }
+
+    pub fn optimistic(&self) -> bool {
+        self.optimistic.unwrap_or(self.state_topic.is_none())
+    }
+
+    /// Map a rendered state payload to `STATE_ON`/`STATE_OFF`/`STATE_UNKNOWN`.
+    pub fn parse_state(&self, payload: &str) -> Option<&'static str> {
+        if payload == self.state_on() {
+            Some(STATE_ON)
+        } else if payload == self.state_off() {
+            Some(STATE_OFF)
+        } else if payload == PAYLOAD_NONE {
+            Some(STATE_UNKNOWN)
+        } else {
+            None
+        }
+    }
+}
+
+fn handle_switch_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_switch: Query<(&MqttSwitchConfiguration, &MQTTStateSubscription, &mut State)>,
+) {
+    let Ok((config, state_sub, mut state)) = q_switch.get_mut(topic_message.entity()) else {
+        return;
+    };
+    if topic_message.event().topic != state_sub.state_topic {
+        return;
+    }
+
+    let payload = try_render_template(&state_sub.value_template, &topic_message.event().payload)
+        .unwrap_or_default();
+    match config.parse_state(&payload) {
+        Some(new_state) => state.update(new_state),
+        None => warn!(
+            "Ignoring switch state payload {:?} on {}",
+            payload,
+            topic_message.event().topic
+        ),
+    }
+}
+
+fn on_switch_command(
+    trigger: Trigger<SwitchCommand>,
+    publisher: MqttPublisher,
+    mut q_switch: Query<(&MqttSwitchConfiguration, &mut State)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state)) = q_switch.get_mut(entity) else {
+        return;
+    };
+
+    let turn_on = match trigger.event() {
+        SwitchCommand::TurnOn => true,
+        SwitchCommand::TurnOff => false,
+        SwitchCommand::Toggle => state.state != STATE_ON,
+    };
+    let payload = if turn_on {
+        config.payload_on()
+    } else {
+        config.payload_off()
+    };
+
+    if let Some(command_topic) = &config.command_topic {
+        if let Err(e) = publisher.publish(entity, command_topic, config.qos, config.retain, payload)
+        {
+            warn!("Failed to publish switch command: {}", e);
+            return;
+        }
+    }
+
+    if config.optimistic() {
+        state.update(if turn_on { STATE_ON } else { STATE_OFF });
+    }
+}
+
+#[test]
+fn test_switch_configuration() {
+    let json = r#"
+    {
+        "name": "Plug",
+        "command_topic": "zigbee2mqtt/plug/set",
+        "state_topic": "zigbee2mqtt/plug",
+        "value_template": "{{ value_json.state }}",
+        "payload_on": "ON",
+        "payload_off": "OFF",
+        "state_off": "off"
+    }
+    "#;
+    let config: MqttSwitchConfiguration = serde_json::from_str(json).unwrap();
+
+    assert!(!config.optimistic());
+    assert_eq!(config.parse_state("ON"), Some(STATE_ON));
+    assert_eq!(config.parse_state("off"), Some(STATE_OFF));
+    assert_eq!(config.parse_state("OFF"), None);
+    assert_eq!(config.parse_state("None"), Some(STATE_UNKNOWN));
+}
@@ -0,0 +1,140 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_template, MQTTPlatformState},
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::{Children, Parent};
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use serde::{Deserialize, Serialize};
+use skep_core::{device::Device, tag::TagScanned};
+
+pub struct MqttTagPlugin;
+
+impl Plugin for MqttTagPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_tag_scanned);
+    }
+}
+
+const DOMAIN: &str = "tag";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_children) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttTagConfiguration>(payload.payload.clone()) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid tag config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            [config.topic.as_str()],
+            0,
+        );
+        commands.entity(entity).insert((config, MQTTPlatformState));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttTagConfiguration {
+    /// Receives the id of each scanned tag
+    pub topic: String,
+    pub value_template: Option<String>,
+}
+
+impl MqttTagConfiguration {
+    /// Extract the tag id from a payload, empty ids are ignored.
+    pub fn parse_tag_id(&self, payload: &[u8]) -> Option<String> {
+        let tag_id = try_render_template(&self.value_template, payload).ok()?;
+        let tag_id = tag_id.trim();
+        (!tag_id.is_empty()).then(|| tag_id.to_string())
+    }
+}
+
+fn handle_tag_scanned(
+    topic_message: Trigger<TopicMessage>,
+    mut commands: Commands,
+    q_tag: Query<(&MqttTagConfiguration, &Parent)>,
+    q_device: Query<(), With<Device>>,
+) {
+    let entity = topic_message.entity();
+    let Ok((config, parent)) = q_tag.get(entity) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.topic != topic {
+        return;
+    }
+    let Some(tag_id) = config.parse_tag_id(&topic_message.event().payload) else {
+        warn!("Ignoring tag payload on {}", topic);
+        return;
+    };
+    if !q_device.contains(parent.get()) {
+        warn!("tag scanner {:?} is not attached to a device", entity);
+        return;
+    }
+
+    debug!("tag scanned on {}: {}", topic, tag_id);
+    commands.trigger(TagScanned {
+        tag_id,
+        device: parent.get(),
+    });
+}
+
+#[test]
+fn test_tag_configuration() {
+    let json = r#"
+    {
+        "topic": "reader/front_door/tag",
+        "value_template": "{{ value_json.PN532.UID }}"
+    }
+    "#;
+    let config: MqttTagConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        config.parse_tag_id(br#"{"PN532": {"UID": "E9F35959", "DATA": "ILOVETASMOTA"}}"#),
+        Some("E9F35959".to_string())
+    );
+
+    let config: MqttTagConfiguration =
+        serde_json::from_str(r#"{"topic": "reader/front_door/tag"}"#).unwrap();
+    assert_eq!(
+        config.parse_tag_id(b" 04:a2:5b:e2\n"),
+        Some("04:a2:5b:e2".to_string())
+    );
+    assert_eq!(config.parse_tag_id(b""), None);
+}
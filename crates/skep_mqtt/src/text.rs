@@ -0,0 +1,259 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_template, try_render_template, MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use skep_core::{constants::STATE_UNKNOWN, states::State};
+
+pub struct MqttTextPlugin;
+
+impl Plugin for MqttTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_text_state)
+        .observe(on_text_command);
+    }
+}
+
+const DOMAIN: &str = "text";
+
+const DEFAULT_MIN_LENGTH: usize = 0;
+const DEFAULT_MAX_LENGTH: usize = 255;
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttTextConfiguration>(payload.payload.clone())
+        {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid text config {}: {}", hash, e);
+                continue;
+            }
+        };
+        if config.min() > config.max() {
+            warn!(
+                "invalid text config {}: min {} must be <= max {}",
+                hash,
+                config.min(),
+                config.max()
+            );
+            continue;
+        }
+        if let Err(e) = config.pattern_regex() {
+            warn!("invalid text pattern {}: {}", hash, e);
+            continue;
+        }
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        cmds.insert((TextAttributes::from(&config), config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TextMode {
+    #[default]
+    Text,
+    Password,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttTextConfiguration {
+    pub command_topic: Option<String>,
+    pub command_template: Option<String>,
+    pub state_topic: Option<String>,
+    pub value_template: Option<String>,
+    /// Minimum length of the text value.
+    pub min: Option<usize>,
+    /// Maximum length of the text value.
+    pub max: Option<usize>,
+    pub mode: Option<TextMode>,
+    /// Regex the whole text value has to match.
+    pub pattern: Option<String>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttTextConfiguration {
+    pub fn min(&self) -> usize {
+        self.min.unwrap_or(DEFAULT_MIN_LENGTH)
+    }
+
+    pub fn max(&self) -> usize {
+        self.max.unwrap_or(DEFAULT_MAX_LENGTH)
+    }
+
+    pub fn optimistic(&self) -> bool {
+        self.optimistic.unwrap_or(false) || self.state_topic.is_none()
+    }
+
+    fn pattern_regex(&self) -> Result<Option<Regex>, regex::Error> {
+        self.pattern
+            .as_ref()
+            .map(|pattern| Regex::new(&format!("^(?:{})$", pattern)))
+            .transpose()
+    }
+
+    /// Check a value against the configured length limits and pattern.
+    pub fn validate(&self, value: &str) -> anyhow::Result<()> {
+        let length = value.chars().count();
+        if length < self.min() || length > self.max() {
+            anyhow::bail!(
+                "length {} is out of range {}-{}",
+                length,
+                self.min(),
+                self.max()
+            );
+        }
+        if let Some(regex) = self.pattern_regex()? {
+            if !regex.is_match(value) {
+                anyhow::bail!("value does not match pattern {}", regex);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Value constraints of a text entity.
+#[derive(Debug, Component, Clone, Serialize)]
+pub struct TextAttributes {
+    pub min: usize,
+    pub max: usize,
+    pub mode: TextMode,
+    pub pattern: Option<String>,
+}
+
+impl From<&MqttTextConfiguration> for TextAttributes {
+    fn from(config: &MqttTextConfiguration) -> Self {
+        Self {
+            min: config.min(),
+            max: config.max(),
+            mode: config.mode.unwrap_or_default(),
+            pattern: config.pattern.clone(),
+        }
+    }
+}
+
+/// Commands accepted by an MQTT text entity, triggered with the text as target.
+#[derive(Debug, Event, Clone, PartialEq, Eq)]
+pub enum TextCommand {
+    SetValue(String),
+}
+
+fn handle_text_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_text: Query<(&MqttTextConfiguration, &mut State)>,
+) {
+    let Ok((config, mut state)) = q_text.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic.as_deref() != Some(topic) {
+        return;
+    }
+    let value = match try_render_template(&config.value_template, &topic_message.event().payload) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("Failed to render text payload on {}: {}", topic, e);
+            return;
+        }
+    };
+    match config.validate(&value) {
+        Ok(()) => state.update(value),
+        Err(e) => warn!("Ignoring text payload {:?} on {}: {}", value, topic, e),
+    }
+}
+
+fn on_text_command(
+    trigger: Trigger<TextCommand>,
+    publisher: MqttPublisher,
+    mut q_text: Query<(&MqttTextConfiguration, &mut State)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state)) = q_text.get_mut(entity) else {
+        return;
+    };
+    let Some(topic) = &config.command_topic else {
+        return;
+    };
+    let TextCommand::SetValue(value) = trigger.event();
+    if let Err(e) = config.validate(value) {
+        warn!("Invalid text value {:?}: {}", value, e);
+        return;
+    }
+
+    let payload = match try_render_command_template(&config.command_template, &json!(value)) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!("Failed to render text command: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = publisher.publish(entity, topic, config.qos, config.retain, payload) {
+        warn!("Failed to publish text command: {}", e);
+        return;
+    }
+    if config.optimistic() {
+        state.update(value);
+    }
+}
+
+#[test]
+fn test_text_configuration() {
+    let json = r#"
+    {
+        "command_topic": "device/pin/set",
+        "min": 4,
+        "max": 6,
+        "mode": "password",
+        "pattern": "[0-9]+"
+    }
+    "#;
+    let config: MqttTextConfiguration = serde_json::from_str(json).unwrap();
+
+    assert!(config.optimistic());
+    assert_eq!(config.mode, Some(TextMode::Password));
+    assert!(config.validate("1234").is_ok());
+    assert!(config.validate("123").is_err());
+    assert!(config.validate("1234567").is_err());
+    assert!(config.validate("12a4").is_err());
+
+    let config: MqttTextConfiguration = serde_json::from_str("{}").unwrap();
+    assert!(config.validate("").is_ok());
+    assert!(config.validate(&"x".repeat(256)).is_err());
+}
@@ -0,0 +1,163 @@
+use crate::MqttConfig;
+use anyhow::Context;
+use bevy_mqtt::rumqttc::{
+    tokio_rustls::rustls::{
+        self,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        pki_types::{CertificateDer, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    },
+    TlsConfiguration,
+};
+use std::sync::Arc;
+
+/// `certificate` value to verify the broker against the public CA roots.
+const CERTIFICATE_AUTO: &str = "auto";
+
+/// The TLS configuration of an MQTT connection, `None` when neither a CA certificate, a client
+/// certificate nor `tls_insecure` is configured. Without a CA file, or with `certificate` set to
+/// `auto`, the broker is verified against the public CA roots.
+pub(crate) fn tls_configuration(config: &MqttConfig) -> anyhow::Result<Option<TlsConfiguration>> {
+    let client_auth = match (&config.client_cert, &config.client_key) {
+        (Some(client_cert), Some(client_key)) => Some((read(client_cert)?, read(client_key)?)),
+        (None, None) => None,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "client_cert and client_key must be configured together"
+            ))
+        }
+    };
+    let insecure = config.tls_insecure.unwrap_or(false);
+    let certificate = config.certificate.as_deref();
+    if certificate.is_none() && client_auth.is_none() && !insecure {
+        return Ok(None);
+    }
+
+    if let Some(certificate) = certificate.filter(|c| !insecure && *c != CERTIFICATE_AUTO) {
+        return Ok(Some(TlsConfiguration::Simple {
+            ca: read(certificate)?,
+            alpn: None,
+            client_auth,
+        }));
+    }
+
+    // Without a CA file the broker is verified against the public roots, unless insecure
+    let builder = ClientConfig::builder();
+    let builder = if insecure {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoCertificateVerification))
+    } else {
+        let mut root_store = RootCertStore::empty();
+        root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        builder.with_root_certificates(root_store)
+    };
+    let client_config = match client_auth {
+        Some((client_cert, client_key)) => {
+            let certs = rustls_pemfile::certs(&mut client_cert.as_slice())
+                .collect::<Result<Vec<_>, _>>()?;
+            let key = rustls_pemfile::private_key(&mut client_key.as_slice())?
+                .ok_or_else(|| anyhow::anyhow!("No private key found in client_key"))?;
+            builder.with_client_auth_cert(certs, key)?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    Ok(Some(TlsConfiguration::Rustls(Arc::new(client_config))))
+}
+
+fn read(path: &str) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("Failed to read {}", path))
+}
+
+/// Accepts any broker certificate, for `tls_insecure`.
+#[derive(Debug)]
+struct NoCertificateVerification;
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![
+            SignatureScheme::RSA_PKCS1_SHA256,
+            SignatureScheme::RSA_PKCS1_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA512,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::ECDSA_NISTP521_SHA512,
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::RSA_PSS_SHA384,
+            SignatureScheme::RSA_PSS_SHA512,
+            SignatureScheme::ED25519,
+        ]
+    }
+}
+
+#[test]
+fn test_tls_configuration() {
+    let config = |json: serde_json::Value| serde_json::from_value::<MqttConfig>(json).unwrap();
+
+    let plain = config(serde_json::json!({"broker": "localhost", "port": 1883}));
+    assert!(tls_configuration(&plain).unwrap().is_none());
+
+    let insecure = config(serde_json::json!({
+        "broker": "localhost",
+        "port": 8883,
+        "tls_insecure": true
+    }));
+    assert!(matches!(
+        tls_configuration(&insecure).unwrap(),
+        Some(TlsConfiguration::Rustls(_))
+    ));
+
+    let public_roots = config(serde_json::json!({
+        "broker": "broker.example.com",
+        "port": 8883,
+        "certificate": "auto"
+    }));
+    assert!(matches!(
+        tls_configuration(&public_roots).unwrap(),
+        Some(TlsConfiguration::Rustls(_))
+    ));
+
+    let missing_ca = config(serde_json::json!({
+        "broker": "localhost",
+        "port": 8883,
+        "certificate": "missing/ca.crt"
+    }));
+    assert!(tls_configuration(&missing_ca).is_err());
+
+    let missing_key = config(serde_json::json!({
+        "broker": "localhost",
+        "port": 8883,
+        "certificate": "ca.crt",
+        "client_cert": "client.crt"
+    }));
+    assert!(tls_configuration(&missing_key).is_err());
+}
@@ -0,0 +1,311 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_template, MQTTPlatformState, MqttPublisher},
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::{Children, Parent};
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use skep_core::{
+    constants::{STATE_OFF, STATE_ON, STATE_UNKNOWN},
+    device::Device,
+    states::State,
+};
+
+pub struct MqttUpdatePlugin;
+
+impl Plugin for MqttUpdatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_update_state)
+        .observe(on_update_command);
+    }
+}
+
+const DOMAIN: &str = "update";
+
+const DEFAULT_PAYLOAD_INSTALL: &str = "install";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    mut q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&mut UpdateAttributes>,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_state, opt_attributes, opt_children) in q_discovery.iter_mut() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttUpdateConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid update config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            config
+                .latest_version_topic
+                .as_deref()
+                .filter(|topic| Some(*topic) != config.state_topic.as_deref()),
+            config.qos.unwrap_or(0),
+        );
+        let mut cmds = commands.entity(entity);
+        match opt_attributes {
+            Some(mut attributes) => attributes.update_from_config(&config),
+            None => {
+                let mut attributes = UpdateAttributes::default();
+                attributes.update_from_config(&config);
+                cmds.insert(attributes);
+            }
+        }
+        cmds.insert((config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateDeviceClass {
+    Firmware,
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttUpdateConfiguration {
+    /// Receives the installed version, or a JSON object with `installed_version`,
+    /// `latest_version`, `title`, `release_summary`, `release_url`, `in_progress` and
+    /// `update_percentage`.
+    pub state_topic: Option<String>,
+    pub value_template: Option<String>,
+    pub latest_version_topic: Option<String>,
+    pub latest_version_template: Option<String>,
+    pub command_topic: Option<String>,
+    pub payload_install: Option<String>,
+    pub title: Option<String>,
+    pub release_summary: Option<String>,
+    pub release_url: Option<String>,
+    pub device_class: Option<UpdateDeviceClass>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttUpdateConfiguration {
+    pub fn payload_install(&self) -> &str {
+        self.payload_install
+            .as_deref()
+            .unwrap_or(DEFAULT_PAYLOAD_INSTALL)
+    }
+}
+
+/// Installed and available versions, the [`State`] is `on` when an update is pending.
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct UpdateAttributes {
+    /// Falls back to the `sw_version` of the [`Device`] when not reported
+    pub installed_version: Option<String>,
+    pub latest_version: Option<String>,
+    pub title: Option<String>,
+    pub release_summary: Option<String>,
+    pub release_url: Option<String>,
+    pub in_progress: bool,
+    pub update_percentage: Option<f32>,
+}
+
+impl UpdateAttributes {
+    fn update_from_config(&mut self, config: &MqttUpdateConfiguration) {
+        self.title = config.title.clone();
+        self.release_summary = config.release_summary.clone();
+        self.release_url = config.release_url.clone();
+    }
+
+    /// Update from a JSON state payload, `null` clears a value.
+    fn update_from_json(&mut self, object: &Map<String, Value>) {
+        let string = |key: &str| object.get(key).map(|v| v.as_str().map(String::from));
+        if let Some(installed_version) = string("installed_version") {
+            self.installed_version = installed_version;
+        }
+        if let Some(latest_version) = string("latest_version") {
+            self.latest_version = latest_version;
+        }
+        if let Some(title) = string("title") {
+            self.title = title;
+        }
+        if let Some(release_summary) = string("release_summary") {
+            self.release_summary = release_summary;
+        }
+        if let Some(release_url) = string("release_url") {
+            self.release_url = release_url;
+        }
+        // `in_progress` is either a flag or the progress in percent
+        match object.get("in_progress") {
+            Some(Value::Bool(in_progress)) => {
+                self.in_progress = *in_progress;
+                if !in_progress {
+                    self.update_percentage = None;
+                }
+            }
+            Some(Value::Number(percentage)) => {
+                self.in_progress = true;
+                self.update_percentage = percentage.as_f64().map(|p| p as f32);
+            }
+            _ => {}
+        }
+        if let Some(percentage) = object.get("update_percentage") {
+            self.update_percentage = percentage.as_f64().map(|p| p as f32);
+        }
+    }
+
+    /// `on` when the latest version differs from the installed one.
+    pub fn resolve_state(&self, sw_version: Option<&str>) -> &'static str {
+        let installed_version = self.installed_version.as_deref().or(sw_version);
+        match (installed_version, self.latest_version.as_deref()) {
+            (Some(installed), Some(latest)) if installed != latest => STATE_ON,
+            (Some(_), Some(_)) => STATE_OFF,
+            _ => STATE_UNKNOWN,
+        }
+    }
+}
+
+/// Commands accepted by an MQTT update entity, triggered with the update as target.
+#[derive(Debug, Event, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateCommand {
+    Install,
+}
+
+fn handle_update_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_update: Query<(
+        &MqttUpdateConfiguration,
+        &mut State,
+        &mut UpdateAttributes,
+        Option<&Parent>,
+    )>,
+    q_device: Query<&Device>,
+) {
+    let Ok((config, mut state, mut attributes, opt_parent)) =
+        q_update.get_mut(topic_message.entity())
+    else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    let payload = &topic_message.event().payload[..];
+    let mut updated = false;
+
+    if config.state_topic.as_deref() == Some(topic) {
+        let value = try_render_template(&config.value_template, payload).unwrap_or_default();
+        match serde_json::from_str::<Value>(&value) {
+            Ok(Value::Object(object)) => attributes.update_from_json(&object),
+            _ if value.is_empty() => attributes.installed_version = None,
+            _ => attributes.installed_version = Some(value),
+        }
+        updated = true;
+    }
+    if config.latest_version_topic.as_deref() == Some(topic) {
+        let value =
+            try_render_template(&config.latest_version_template, payload).unwrap_or_default();
+        attributes.latest_version = (!value.is_empty()).then_some(value);
+        updated = true;
+    }
+
+    if updated {
+        let sw_version = opt_parent
+            .and_then(|parent| q_device.get(parent.get()).ok())
+            .and_then(|device| device.sw_version.as_deref());
+        state.update(attributes.resolve_state(sw_version));
+    }
+}
+
+fn on_update_command(
+    trigger: Trigger<UpdateCommand>,
+    publisher: MqttPublisher,
+    q_update: Query<&MqttUpdateConfiguration>,
+) {
+    let entity = trigger.entity();
+    let Ok(config) = q_update.get(entity) else {
+        return;
+    };
+    let UpdateCommand::Install = trigger.event();
+
+    let Some(topic) = &config.command_topic else {
+        warn!("Update {:?} has no command topic", entity);
+        return;
+    };
+    if let Err(e) = publisher.publish(
+        entity,
+        topic,
+        config.qos,
+        config.retain,
+        config.payload_install(),
+    ) {
+        warn!("Failed to publish update command: {}", e);
+    }
+}
+
+#[test]
+fn test_update_configuration() {
+    let json = r#"
+    {
+        "state_topic": "tasmota/plug/update",
+        "command_topic": "tasmota/plug/cmnd/upgrade",
+        "payload_install": "1",
+        "device_class": "firmware"
+    }
+    "#;
+    let config: MqttUpdateConfiguration = serde_json::from_str(json).unwrap();
+    assert_eq!(config.payload_install(), "1");
+    assert_eq!(config.device_class, Some(UpdateDeviceClass::Firmware));
+
+    let mut attributes = UpdateAttributes::default();
+    attributes.update_from_config(&config);
+    assert_eq!(attributes.resolve_state(None), STATE_UNKNOWN);
+
+    let object = serde_json::from_str::<Map<String, Value>>(
+        r#"{"installed_version": "13.4.0", "latest_version": "14.1.0", "release_url": "https://github.com/arendst/Tasmota/releases", "in_progress": 40}"#,
+    )
+    .unwrap();
+    attributes.update_from_json(&object);
+    assert_eq!(attributes.resolve_state(None), STATE_ON);
+    assert!(attributes.in_progress);
+    assert_eq!(attributes.update_percentage, Some(40.0));
+
+    let object = serde_json::from_str::<Map<String, Value>>(
+        r#"{"installed_version": null, "in_progress": false}"#,
+    )
+    .unwrap();
+    attributes.update_from_json(&object);
+    assert!(!attributes.in_progress);
+    assert_eq!(attributes.update_percentage, None);
+    assert_eq!(attributes.resolve_state(None), STATE_UNKNOWN);
+    assert_eq!(attributes.resolve_state(Some("14.1.0")), STATE_OFF);
+}
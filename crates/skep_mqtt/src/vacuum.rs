@@ -0,0 +1,371 @@
+use crate::{
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{MQTTAttributesBlocked, MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use skep_core::{
+    constants::{STATE_IDLE, STATE_PAUSED, STATE_UNKNOWN},
+    states::State,
+};
+use std::collections::HashSet;
+use strum_macros::{Display, EnumString};
+
+lazy_static! {
+    static ref MQTT_VACUUM_ATTRIBUTES_BLOCKED: HashSet<&'static str> = HashSet::from([
+        "battery_icon",
+        "battery_level",
+        "fan_speed",
+        "fan_speed_list",
+    ]);
+}
+
+pub struct MqttVacuumPlugin;
+
+impl Plugin for MqttVacuumPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_vacuum_state)
+        .observe(on_vacuum_command);
+    }
+}
+
+const DOMAIN: &str = "vacuum";
+
+const DEFAULT_PAYLOAD_START: &str = "start";
+const DEFAULT_PAYLOAD_PAUSE: &str = "pause";
+const DEFAULT_PAYLOAD_STOP: &str = "stop";
+const DEFAULT_PAYLOAD_RETURN_TO_BASE: &str = "return_to_base";
+const DEFAULT_PAYLOAD_LOCATE: &str = "locate";
+const DEFAULT_PAYLOAD_CLEAN_SPOT: &str = "clean_spot";
+
+pub const STATE_CLEANING: &str = "cleaning";
+pub const STATE_DOCKED: &str = "docked";
+pub const STATE_RETURNING: &str = "returning";
+pub const STATE_ERROR: &str = "error";
+
+const VACUUM_STATES: &[&str] = &[
+    STATE_CLEANING,
+    STATE_DOCKED,
+    STATE_PAUSED,
+    STATE_IDLE,
+    STATE_RETURNING,
+    STATE_ERROR,
+];
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    mut q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&mut VacuumAttributes>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state, opt_attributes) in q_discovery.iter_mut() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttVacuumConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid vacuum config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        match opt_attributes {
+            Some(mut attributes) => attributes.update_from_config(&config),
+            None => {
+                let mut attributes = VacuumAttributes::default();
+                attributes.update_from_config(&config);
+                cmds.insert(attributes);
+            }
+        }
+        cmds.insert((
+            config,
+            MQTTPlatformState,
+            MQTTAttributesBlocked(&MQTT_VACUUM_ATTRIBUTES_BLOCKED),
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum VacuumFeature {
+    Start,
+    Stop,
+    Pause,
+    ReturnHome,
+    Battery,
+    Status,
+    Locate,
+    CleanSpot,
+    FanSpeed,
+    SendCommand,
+}
+
+fn default_supported_features() -> Vec<VacuumFeature> {
+    vec![
+        VacuumFeature::Start,
+        VacuumFeature::Stop,
+        VacuumFeature::ReturnHome,
+        VacuumFeature::Status,
+        VacuumFeature::Battery,
+        VacuumFeature::CleanSpot,
+    ]
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttVacuumConfiguration {
+    pub command_topic: Option<String>,
+    /// Receives a JSON object with `state`, `battery_level` and `fan_speed`.
+    pub state_topic: Option<String>,
+    pub send_command_topic: Option<String>,
+    pub set_fan_speed_topic: Option<String>,
+    #[serde(default)]
+    pub fan_speed_list: Vec<String>,
+    pub payload_start: Option<String>,
+    pub payload_pause: Option<String>,
+    pub payload_stop: Option<String>,
+    pub payload_return_to_base: Option<String>,
+    pub payload_locate: Option<String>,
+    pub payload_clean_spot: Option<String>,
+    #[serde(default = "default_supported_features")]
+    pub supported_features: Vec<VacuumFeature>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttVacuumConfiguration {
+    /// The feature a command requires and the payload it sends to `command_topic`.
+    fn command_payload(&self, command: &VacuumCommand) -> Option<(VacuumFeature, &str)> {
+        let (feature, payload, default) = match command {
+            VacuumCommand::Start => (
+                VacuumFeature::Start,
+                &self.payload_start,
+                DEFAULT_PAYLOAD_START,
+            ),
+            VacuumCommand::Pause => (
+                VacuumFeature::Pause,
+                &self.payload_pause,
+                DEFAULT_PAYLOAD_PAUSE,
+            ),
+            VacuumCommand::Stop => (
+                VacuumFeature::Stop,
+                &self.payload_stop,
+                DEFAULT_PAYLOAD_STOP,
+            ),
+            VacuumCommand::ReturnToBase => (
+                VacuumFeature::ReturnHome,
+                &self.payload_return_to_base,
+                DEFAULT_PAYLOAD_RETURN_TO_BASE,
+            ),
+            VacuumCommand::Locate => (
+                VacuumFeature::Locate,
+                &self.payload_locate,
+                DEFAULT_PAYLOAD_LOCATE,
+            ),
+            VacuumCommand::CleanSpot => (
+                VacuumFeature::CleanSpot,
+                &self.payload_clean_spot,
+                DEFAULT_PAYLOAD_CLEAN_SPOT,
+            ),
+            VacuumCommand::SetFanSpeed(_) | VacuumCommand::SendCommand { .. } => return None,
+        };
+        Some((feature, payload.as_deref().unwrap_or(default)))
+    }
+}
+
+/// Battery and fan speed reported next to the vacuum [`State`].
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct VacuumAttributes {
+    pub battery_level: Option<u8>,
+    pub fan_speed: Option<String>,
+    pub fan_speed_list: Vec<String>,
+    pub supported_features: Vec<VacuumFeature>,
+}
+
+impl VacuumAttributes {
+    fn update_from_config(&mut self, config: &MqttVacuumConfiguration) {
+        self.fan_speed_list = config.fan_speed_list.clone();
+        self.supported_features = config.supported_features.clone();
+    }
+
+    /// Update from a state payload and return the new vacuum state, if any.
+    fn update_from_json(&mut self, object: &Map<String, Value>) -> Option<&'static str> {
+        if let Some(battery_level) = object.get("battery_level").and_then(Value::as_f64) {
+            self.battery_level = Some(battery_level.clamp(0.0, 100.0) as u8);
+        }
+        if let Some(fan_speed) = object.get("fan_speed").and_then(Value::as_str) {
+            if self.fan_speed_list.iter().any(|speed| speed == fan_speed) {
+                self.fan_speed = Some(fan_speed.to_string());
+            } else {
+                warn!("Ignoring unsupported vacuum fan speed {:?}", fan_speed);
+            }
+        }
+        let state = object.get("state")?;
+        match state.as_str() {
+            Some(state) => VACUUM_STATES.iter().find(|s| **s == state).copied(),
+            None if state.is_null() => Some(STATE_UNKNOWN),
+            None => None,
+        }
+    }
+}
+
+/// Commands accepted by an MQTT vacuum entity, triggered with the vacuum as target.
+#[derive(Debug, Event, Clone, PartialEq)]
+pub enum VacuumCommand {
+    Start,
+    Pause,
+    Stop,
+    ReturnToBase,
+    Locate,
+    CleanSpot,
+    SetFanSpeed(String),
+    /// Send a raw command to `send_command_topic`, with `params` merged into a JSON payload
+    SendCommand {
+        command: String,
+        params: Option<Map<String, Value>>,
+    },
+}
+
+fn handle_vacuum_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_vacuum: Query<(&MqttVacuumConfiguration, &mut State, &mut VacuumAttributes)>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_vacuum.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic.as_deref() != Some(topic) {
+        return;
+    }
+    let payload = &topic_message.event().payload;
+    let Ok(object) = serde_json::from_slice::<Map<String, Value>>(payload) else {
+        warn!("Ignoring vacuum state payload {:?} on {}", payload, topic);
+        return;
+    };
+    if let Some(new_state) = attributes.update_from_json(&object) {
+        state.update(new_state);
+    }
+}
+
+fn on_vacuum_command(
+    trigger: Trigger<VacuumCommand>,
+    publisher: MqttPublisher,
+    q_vacuum: Query<&MqttVacuumConfiguration>,
+) {
+    let entity = trigger.entity();
+    let Ok(config) = q_vacuum.get(entity) else {
+        return;
+    };
+    let command = trigger.event();
+
+    let (feature, topic, payload) = match command {
+        VacuumCommand::SetFanSpeed(fan_speed) => {
+            if !config.fan_speed_list.contains(fan_speed) {
+                warn!("Unsupported vacuum fan speed: {}", fan_speed);
+                return;
+            }
+            (
+                VacuumFeature::FanSpeed,
+                &config.set_fan_speed_topic,
+                fan_speed.clone(),
+            )
+        }
+        VacuumCommand::SendCommand { command, params } => {
+            let payload = match params {
+                Some(params) => {
+                    let mut object = params.clone();
+                    object.insert("command".to_string(), Value::String(command.clone()));
+                    Value::Object(object).to_string()
+                }
+                None => command.clone(),
+            };
+            (
+                VacuumFeature::SendCommand,
+                &config.send_command_topic,
+                payload,
+            )
+        }
+        command => match config.command_payload(command) {
+            Some((feature, payload)) => (feature, &config.command_topic, payload.to_string()),
+            None => return,
+        },
+    };
+
+    if !config.supported_features.contains(&feature) {
+        warn!("Vacuum {:?} does not support {}", entity, feature);
+        return;
+    }
+    let Some(topic) = topic else {
+        return;
+    };
+    if let Err(e) = publisher.publish(entity, topic, config.qos, config.retain, payload) {
+        warn!("Failed to publish vacuum command: {}", e);
+    }
+}
+
+#[test]
+fn test_vacuum_configuration() {
+    let json = r#"
+    {
+        "command_topic": "vacuum/command",
+        "state_topic": "vacuum/state",
+        "set_fan_speed_topic": "vacuum/set_fan_speed",
+        "fan_speed_list": ["min", "medium", "max"],
+        "payload_return_to_base": "dock",
+        "supported_features": ["start", "pause", "return_home", "fan_speed"]
+    }
+    "#;
+    let config: MqttVacuumConfiguration = serde_json::from_str(json).unwrap();
+
+    assert_eq!(
+        config.command_payload(&VacuumCommand::ReturnToBase),
+        Some((VacuumFeature::ReturnHome, "dock"))
+    );
+    assert_eq!(
+        config.command_payload(&VacuumCommand::Pause),
+        Some((VacuumFeature::Pause, "pause"))
+    );
+
+    let mut attributes = VacuumAttributes::default();
+    attributes.update_from_config(&config);
+    let object = serde_json::from_str::<Map<String, Value>>(
+        r#"{"state": "cleaning", "battery_level": 61, "fan_speed": "max"}"#,
+    )
+    .unwrap();
+    assert_eq!(attributes.update_from_json(&object), Some(STATE_CLEANING));
+    assert_eq!(attributes.battery_level, Some(61));
+    assert_eq!(attributes.fan_speed.as_deref(), Some("max"));
+
+    let object = serde_json::from_str::<Map<String, Value>>(r#"{"state": "mopping"}"#).unwrap();
+    assert_eq!(attributes.update_from_json(&object), None);
+}
@@ -0,0 +1,321 @@
+use crate::{
+    constants::PAYLOAD_NONE,
+    cover::{from_percentage, to_percentage},
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{try_render_command_template, try_render_template, MQTTPlatformState, MqttPublisher},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::{debug, warn};
+use bevy_mqtt::TopicMessage;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use skep_core::{
+    constants::{STATE_CLOSED, STATE_CLOSING, STATE_OPEN, STATE_OPENING, STATE_UNKNOWN},
+    states::State,
+};
+
+pub struct MqttValvePlugin;
+
+impl Plugin for MqttValvePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_valve_state)
+        .observe(on_valve_command);
+    }
+}
+
+const DOMAIN: &str = "valve";
+
+const DEFAULT_PAYLOAD_OPEN: &str = "OPEN";
+const DEFAULT_PAYLOAD_CLOSE: &str = "CLOSE";
+const DEFAULT_POSITION_OPEN: i32 = 100;
+const DEFAULT_POSITION_CLOSED: i32 = 0;
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Has<ValveAttributes>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+) {
+    for (entity, hash, payload, opt_state, has_attributes) in q_discovery.iter() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config = match serde_json::from_value::<MqttValveConfiguration>(payload.payload.clone())
+        {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("invalid valve config {}: {}", hash, e);
+                continue;
+            }
+        };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        let mut cmds = commands.entity(entity);
+        cmds.insert((config, MQTTPlatformState));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+        if !has_attributes {
+            cmds.insert(ValveAttributes::default());
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone, Default)]
+pub struct MqttValveConfiguration {
+    pub command_topic: Option<String>,
+    pub command_template: Option<String>,
+    pub state_topic: Option<String>,
+    pub value_template: Option<String>,
+    pub payload_open: Option<String>,
+    pub payload_close: Option<String>,
+    /// The command payload that stops the valve, the stop command is unsupported when not set.
+    pub payload_stop: Option<String>,
+    pub state_open: Option<String>,
+    pub state_opening: Option<String>,
+    pub state_closed: Option<String>,
+    pub state_closing: Option<String>,
+    /// When set, commands and states carry a position instead of open/close payloads.
+    #[serde(default)]
+    pub reports_position: bool,
+    pub position_open: Option<i32>,
+    pub position_closed: Option<i32>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttValveConfiguration {
+    pub fn payload_open(&self) -> &str {
+        self.payload_open.as_deref().unwrap_or(DEFAULT_PAYLOAD_OPEN)
+    }
+
+    pub fn payload_close(&self) -> &str {
+        self.payload_close
+            .as_deref()
+            .unwrap_or(DEFAULT_PAYLOAD_CLOSE)
+    }
+
+    pub fn position_open(&self) -> i32 {
+        self.position_open.unwrap_or(DEFAULT_POSITION_OPEN)
+    }
+
+    pub fn position_closed(&self) -> i32 {
+        self.position_closed.unwrap_or(DEFAULT_POSITION_CLOSED)
+    }
+
+    pub fn optimistic(&self) -> bool {
+        self.optimistic.unwrap_or(false) || self.state_topic.is_none()
+    }
+
+    /// Map a rendered state payload to one of the valve `STATE_*` values.
+    pub fn parse_state(&self, payload: &str) -> Option<&'static str> {
+        if payload == self.state_open.as_deref().unwrap_or(STATE_OPEN) {
+            Some(STATE_OPEN)
+        } else if payload == self.state_opening.as_deref().unwrap_or(STATE_OPENING) {
+            Some(STATE_OPENING)
+        } else if payload == self.state_closed.as_deref().unwrap_or(STATE_CLOSED) {
+            Some(STATE_CLOSED)
+        } else if payload == self.state_closing.as_deref().unwrap_or(STATE_CLOSING) {
+            Some(STATE_CLOSING)
+        } else if payload == PAYLOAD_NONE {
+            Some(STATE_UNKNOWN)
+        } else {
+            None
+        }
+    }
+
+    /// Split a state payload into its state and position parts. A valve reporting its position
+    /// may send a bare position or a `{"state": .., "position": ..}` object.
+    pub fn parse_payload(&self, payload: &str) -> (Option<&'static str>, Option<u8>) {
+        if !self.reports_position {
+            return (self.parse_state(payload), None);
+        }
+        match serde_json::from_str::<Value>(payload) {
+            Ok(Value::Number(position)) => (
+                None,
+                position
+                    .as_f64()
+                    .map(|position| self.position_to_percentage(position)),
+            ),
+            Ok(Value::Object(object)) => (
+                object
+                    .get("state")
+                    .and_then(Value::as_str)
+                    .and_then(|state| self.parse_state(state)),
+                object
+                    .get("position")
+                    .and_then(Value::as_f64)
+                    .map(|position| self.position_to_percentage(position)),
+            ),
+            _ => (self.parse_state(payload), None),
+        }
+    }
+
+    /// Convert a device position to a 0-100 percentage.
+    pub fn position_to_percentage(&self, position: f64) -> u8 {
+        to_percentage(
+            position,
+            self.position_closed() as f64,
+            self.position_open() as f64,
+        )
+    }
+
+    /// Convert a 0-100 percentage to a device position.
+    pub fn percentage_to_position(&self, percentage: u8) -> i32 {
+        from_percentage(percentage, self.position_closed(), self.position_open())
+    }
+}
+
+/// Current valve position as a 0-100 percentage, 0 being closed.
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct ValveAttributes {
+    pub current_position: Option<u8>,
+}
+
+/// Commands accepted by an MQTT valve entity, triggered with the valve as target.
+#[derive(Debug, Event, Clone, Copy, PartialEq, Eq)]
+pub enum ValveCommand {
+    Open,
+    Close,
+    Stop,
+    /// Move the valve to a 0-100 position, only supported when `reports_position` is set
+    SetPosition(u8),
+}
+
+fn handle_valve_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_valve: Query<(&MqttValveConfiguration, &mut State, &mut ValveAttributes)>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_valve.get_mut(topic_message.entity()) else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    if config.state_topic.as_deref() != Some(topic) {
+        return;
+    }
+    let value = try_render_template(&config.value_template, &topic_message.event().payload)
+        .unwrap_or_default();
+
+    let (new_state, position) = config.parse_payload(&value);
+    if position.is_some() {
+        attributes.current_position = position;
+    }
+    match (new_state, position) {
+        (Some(new_state), _) => state.update(new_state),
+        (None, Some(0)) => state.update(STATE_CLOSED),
+        (None, Some(_)) => state.update(STATE_OPEN),
+        (None, None) => warn!("Ignoring valve state payload {:?} on {}", value, topic),
+    }
+}
+
+fn on_valve_command(
+    trigger: Trigger<ValveCommand>,
+    publisher: MqttPublisher,
+    mut q_valve: Query<(&MqttValveConfiguration, &mut State, &mut ValveAttributes)>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state, mut attributes)) = q_valve.get_mut(entity) else {
+        return;
+    };
+    let Some(topic) = &config.command_topic else {
+        return;
+    };
+    let command = *trigger.event();
+
+    let (value, percentage) = match (command, config.reports_position) {
+        (ValveCommand::Open, false) => (json!(config.payload_open()), Some(100)),
+        (ValveCommand::Close, false) => (json!(config.payload_close()), Some(0)),
+        (ValveCommand::Open, true) => (json!(config.position_open()), Some(100)),
+        (ValveCommand::Close, true) => (json!(config.position_closed()), Some(0)),
+        (ValveCommand::SetPosition(percentage), true) => {
+            let percentage = percentage.min(100);
+            (
+                json!(config.percentage_to_position(percentage)),
+                Some(percentage),
+            )
+        }
+        (ValveCommand::SetPosition(_), false) => {
+            warn!("Valve {:?} does not report its position", entity);
+            return;
+        }
+        (ValveCommand::Stop, _) => match &config.payload_stop {
+            Some(payload) => (json!(payload), None),
+            None => {
+                warn!("Valve {:?} does not support stop", entity);
+                return;
+            }
+        },
+    };
+
+    let payload = match try_render_command_template(&config.command_template, &value) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!("Failed to render valve command: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = publisher.publish(entity, topic, config.qos, config.retain, payload) {
+        warn!("Failed to publish valve command: {}", e);
+        return;
+    }
+
+    if config.optimistic() {
+        if let Some(percentage) = percentage {
+            state.update(if percentage == 0 {
+                STATE_CLOSED
+            } else {
+                STATE_OPEN
+            });
+            if config.reports_position {
+                attributes.current_position = Some(percentage);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_valve_configuration() {
+    let json = r#"
+    {
+        "command_topic": "valve/set",
+        "state_topic": "valve/state",
+        "reports_position": true,
+        "position_open": 255
+    }
+    "#;
+    let config: MqttValveConfiguration = serde_json::from_str(json).unwrap();
+
+    assert!(!config.optimistic());
+    assert_eq!(config.parse_payload("51"), (None, Some(20)));
+    assert_eq!(
+        config.parse_payload(r#"{"state": "opening", "position": 255}"#),
+        (Some(STATE_OPENING), Some(100))
+    );
+    assert_eq!(config.parse_payload("closed"), (Some(STATE_CLOSED), None));
+    assert_eq!(config.percentage_to_position(100), 255);
+
+    let config: MqttValveConfiguration = serde_json::from_str("{}").unwrap();
+    assert!(config.optimistic());
+    assert!(config.payload_stop.is_none());
+    assert_eq!(config.parse_payload("51"), (None, None));
+}
@@ -0,0 +1,386 @@
+use crate::{
+    climate::{render_float, render_option},
+    constants::{DEFAULT_PAYLOAD_OFF, DEFAULT_PAYLOAD_ON},
+    discovery::{MQTTDiscoveryHash, MQTTDiscoveryPayload},
+    entity::{
+        try_render_command_template, MQTTAttributesBlocked, MQTTPlatformState, MqttPublisher,
+    },
+    subscription::{update_platform_subscriptions, MQTTPlatformTopic},
+    SkepMqttPlatform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::Children;
+use bevy_log::{debug, warn};
+use bevy_mqtt::{SubscribeTopic, TopicMessage};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use skep_core::{constants::STATE_UNKNOWN, states::State};
+use std::collections::HashSet;
+
+lazy_static! {
+    static ref MQTT_WATER_HEATER_ATTRIBUTES_BLOCKED: HashSet<&'static str> = HashSet::from([
+        "current_temperature",
+        "max_temp",
+        "min_temp",
+        "operation_list",
+        "operation_mode",
+        "target_temp_high",
+        "target_temp_low",
+        "temperature",
+    ]);
+}
+
+pub struct MqttWaterHeaterPlugin;
+
+impl Plugin for MqttWaterHeaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (create_or_update_discovery_payload, on_mqtt_platform_added),
+        )
+        .observe(handle_water_heater_state)
+        .observe(on_water_heater_command);
+    }
+}
+
+const DOMAIN: &str = "water_heater";
+
+const DEFAULT_MIN_TEMP_CELSIUS: f32 = 43.3;
+const DEFAULT_MAX_TEMP_CELSIUS: f32 = 60.0;
+const DEFAULT_MIN_TEMP_FAHRENHEIT: f32 = 110.0;
+const DEFAULT_MAX_TEMP_FAHRENHEIT: f32 = 140.0;
+const TEMP_FAHRENHEIT: &str = "°F";
+const MODE_OFF: &str = "off";
+
+fn on_mqtt_platform_added(mut q_platform: Query<&mut SkepMqttPlatform, Added<SkepMqttPlatform>>) {
+    for mut platform in q_platform.iter_mut() {
+        platform.platforms_loaded.insert(DOMAIN.to_string());
+    }
+}
+
+fn create_or_update_discovery_payload(
+    mut commands: Commands,
+    mut q_discovery: Query<
+        (
+            Entity,
+            &MQTTDiscoveryHash,
+            &MQTTDiscoveryPayload,
+            Option<&State>,
+            Option<&mut WaterHeaterAttributes>,
+            Option<&Children>,
+        ),
+        Changed<MQTTDiscoveryPayload>,
+    >,
+    q_platform_topic: Query<&SubscribeTopic, With<MQTTPlatformTopic>>,
+) {
+    for (entity, hash, payload, opt_state, opt_attributes, opt_children) in q_discovery.iter_mut() {
+        if hash.component != DOMAIN {
+            continue;
+        }
+        let config =
+            match serde_json::from_value::<MqttWaterHeaterConfiguration>(payload.payload.clone()) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("invalid water_heater config {}: {}", hash, e);
+                    continue;
+                }
+            };
+        debug!("create_or_update_discovery_payload {}: {:?}", hash, config);
+
+        update_platform_subscriptions(
+            &mut commands,
+            entity,
+            opt_children,
+            &q_platform_topic,
+            [
+                &config.mode_state_topic,
+                &config.temperature_state_topic,
+                &config.current_temperature_topic,
+            ]
+            .into_iter()
+            .flatten()
+            .map(String::as_str),
+            config.qos.unwrap_or(0),
+        );
+        let mut cmds = commands.entity(entity);
+        match opt_attributes {
+            Some(mut attributes) => attributes.update_from_config(&config),
+            None => {
+                let mut attributes = WaterHeaterAttributes {
+                    target_temperature: config.initial,
+                    ..Default::default()
+                };
+                attributes.update_from_config(&config);
+                cmds.insert(attributes);
+            }
+        }
+        cmds.insert((
+            config,
+            MQTTPlatformState,
+            MQTTAttributesBlocked(&MQTT_WATER_HEATER_ATTRIBUTES_BLOCKED),
+        ));
+        if opt_state.is_none() {
+            cmds.insert(State::new(STATE_UNKNOWN.to_string()));
+        }
+    }
+}
+
+fn default_modes() -> Vec<String> {
+    [
+        "off",
+        "eco",
+        "electric",
+        "gas",
+        "heat_pump",
+        "high_demand",
+        "performance",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+#[derive(Debug, Serialize, Deserialize, Component, Clone)]
+pub struct MqttWaterHeaterConfiguration {
+    pub mode_command_topic: Option<String>,
+    pub mode_command_template: Option<String>,
+    pub mode_state_topic: Option<String>,
+    pub mode_state_template: Option<String>,
+    /// Supported operation modes
+    #[serde(default = "default_modes")]
+    pub modes: Vec<String>,
+    pub temperature_command_topic: Option<String>,
+    pub temperature_command_template: Option<String>,
+    pub temperature_state_topic: Option<String>,
+    pub temperature_state_template: Option<String>,
+    pub current_temperature_topic: Option<String>,
+    pub current_temperature_template: Option<String>,
+    pub power_command_topic: Option<String>,
+    pub power_command_template: Option<String>,
+    pub payload_on: Option<String>,
+    pub payload_off: Option<String>,
+    /// Initial target temperature
+    pub initial: Option<f32>,
+    pub min_temp: Option<f32>,
+    pub max_temp: Option<f32>,
+    pub precision: Option<f32>,
+    pub temperature_unit: Option<String>,
+    pub optimistic: Option<bool>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+impl MqttWaterHeaterConfiguration {
+    pub fn payload_on(&self) -> &str {
+        self.payload_on.as_deref().unwrap_or(DEFAULT_PAYLOAD_ON)
+    }
+
+    pub fn payload_off(&self) -> &str {
+        self.payload_off.as_deref().unwrap_or(DEFAULT_PAYLOAD_OFF)
+    }
+
+    fn is_fahrenheit(&self) -> bool {
+        self.temperature_unit.as_deref() == Some(TEMP_FAHRENHEIT)
+    }
+
+    pub fn min_temp(&self) -> f32 {
+        self.min_temp.unwrap_or(if self.is_fahrenheit() {
+            DEFAULT_MIN_TEMP_FAHRENHEIT
+        } else {
+            DEFAULT_MIN_TEMP_CELSIUS
+        })
+    }
+
+    pub fn max_temp(&self) -> f32 {
+        self.max_temp.unwrap_or(if self.is_fahrenheit() {
+            DEFAULT_MAX_TEMP_FAHRENHEIT
+        } else {
+            DEFAULT_MAX_TEMP_CELSIUS
+        })
+    }
+
+    fn optimistic_for(&self, state_topic: &Option<String>) -> bool {
+        self.optimistic.unwrap_or(false) || state_topic.is_none()
+    }
+}
+
+/// Current water heater temperatures, the [`State`] holds the operation mode.
+#[derive(Debug, Component, Clone, Default, Serialize)]
+pub struct WaterHeaterAttributes {
+    pub current_temperature: Option<f32>,
+    pub target_temperature: Option<f32>,
+    pub operation_list: Vec<String>,
+    pub min_temp: f32,
+    pub max_temp: f32,
+    pub precision: Option<f32>,
+    pub temperature_unit: Option<String>,
+}
+
+impl WaterHeaterAttributes {
+    fn update_from_config(&mut self, config: &MqttWaterHeaterConfiguration) {
+        self.operation_list = config.modes.clone();
+        self.min_temp = config.min_temp();
+        self.max_temp = config.max_temp();
+        self.precision = config.precision;
+        self.temperature_unit = config.temperature_unit.clone();
+    }
+}
+
+/// Commands accepted by an MQTT water heater entity, triggered with the water heater as target.
+#[derive(Debug, Event, Clone, PartialEq)]
+pub enum WaterHeaterCommand {
+    SetOperationMode(String),
+    SetTemperature(f32),
+    TurnOn,
+    TurnOff,
+}
+
+fn handle_water_heater_state(
+    topic_message: Trigger<TopicMessage>,
+    mut q_water_heater: Query<(
+        &MqttWaterHeaterConfiguration,
+        &mut State,
+        &mut WaterHeaterAttributes,
+    )>,
+) {
+    let Ok((config, mut state, mut attributes)) = q_water_heater.get_mut(topic_message.entity())
+    else {
+        return;
+    };
+    let topic = topic_message.event().topic.as_str();
+    let payload = &topic_message.event().payload[..];
+    let is_topic = |t: &Option<String>| t.as_deref() == Some(topic);
+
+    if is_topic(&config.mode_state_topic) {
+        if let Some(mode) = render_option(&config.mode_state_template, payload, &config.modes) {
+            state.update(mode.as_deref().unwrap_or(STATE_UNKNOWN));
+        }
+    }
+    if is_topic(&config.temperature_state_topic) {
+        attributes.target_temperature = render_float(&config.temperature_state_template, payload);
+    }
+    if is_topic(&config.current_temperature_topic) {
+        attributes.current_temperature =
+            render_float(&config.current_temperature_template, payload);
+    }
+}
+
+fn on_water_heater_command(
+    trigger: Trigger<WaterHeaterCommand>,
+    publisher: MqttPublisher,
+    mut q_water_heater: Query<(
+        &MqttWaterHeaterConfiguration,
+        &mut State,
+        &mut WaterHeaterAttributes,
+    )>,
+) {
+    let entity = trigger.entity();
+    let Ok((config, mut state, mut attributes)) = q_water_heater.get_mut(entity) else {
+        return;
+    };
+
+    let publish = |topic: &Option<String>, template: &Option<String>, value: Value| -> bool {
+        let Some(topic) = topic else {
+            return false;
+        };
+        let payload = match try_render_command_template(template, &value) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to render water_heater command for {}: {}", topic, e);
+                return false;
+            }
+        };
+        match publisher.publish(entity, topic, config.qos, config.retain, payload) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Failed to publish water_heater command: {}", e);
+                false
+            }
+        }
+    };
+
+    match trigger.event() {
+        WaterHeaterCommand::SetOperationMode(mode) => {
+            if !config.modes.contains(mode) {
+                warn!("Unsupported water_heater mode: {}", mode);
+                return;
+            }
+            if publish(
+                &config.mode_command_topic,
+                &config.mode_command_template,
+                json!(mode),
+            ) && config.optimistic_for(&config.mode_state_topic)
+            {
+                state.update(mode);
+            }
+        }
+        WaterHeaterCommand::SetTemperature(temperature) => {
+            let temperature = temperature.clamp(attributes.min_temp, attributes.max_temp);
+            if publish(
+                &config.temperature_command_topic,
+                &config.temperature_command_template,
+                json!(temperature),
+            ) && config.optimistic_for(&config.temperature_state_topic)
+            {
+                attributes.target_temperature = Some(temperature);
+            }
+        }
+        WaterHeaterCommand::TurnOn => {
+            publish(
+                &config.power_command_topic,
+                &config.power_command_template,
+                json!(config.payload_on()),
+            );
+        }
+        WaterHeaterCommand::TurnOff => {
+            // Without a power topic, turning off falls back to the `off` operation mode
+            if config.power_command_topic.is_some() {
+                publish(
+                    &config.power_command_topic,
+                    &config.power_command_template,
+                    json!(config.payload_off()),
+                );
+            } else if config.modes.iter().any(|mode| mode == MODE_OFF)
+                && publish(
+                    &config.mode_command_topic,
+                    &config.mode_command_template,
+                    json!(MODE_OFF),
+                )
+                && config.optimistic_for(&config.mode_state_topic)
+            {
+                state.update(MODE_OFF);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_water_heater_configuration() {
+    let json = r#"
+    {
+        "mode_command_topic": "boiler/mode/set",
+        "mode_state_topic": "boiler/mode",
+        "temperature_command_topic": "boiler/temperature/set",
+        "temperature_unit": "°F",
+        "modes": ["off", "eco", "performance"]
+    }
+    "#;
+    let config: MqttWaterHeaterConfiguration = serde_json::from_str(json).unwrap();
+
+    assert_eq!(config.min_temp(), 110.0);
+    assert_eq!(config.max_temp(), 140.0);
+    assert!(!config.optimistic_for(&config.mode_state_topic));
+    assert!(config.optimistic_for(&config.temperature_state_topic));
+    assert_eq!(
+        render_option(&None, b"eco", &config.modes),
+        Some(Some("eco".to_string()))
+    );
+    assert_eq!(render_option(&None, b"gas", &config.modes), None);
+
+    let config: MqttWaterHeaterConfiguration = serde_json::from_str("{}").unwrap();
+    assert_eq!(config.modes.len(), 7);
+    assert_eq!(config.min_temp(), 43.3);
+    assert_eq!(config.payload_on(), "ON");
+}
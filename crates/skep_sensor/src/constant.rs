@@ -139,6 +139,29 @@ impl SensorDeviceClass {
 
         s.map(|v| v.to_string())
     }
+
+    /// Whether the state of the device class is a number.
+    pub fn is_numeric(&self) -> bool {
+        !matches!(
+            self,
+            SensorDeviceClass::Date | SensorDeviceClass::Enum | SensorDeviceClass::Timestamp
+        )
+    }
+}
+
+#[derive(
+    Debug, EnumString, Display, PartialEq, Clone, Copy, Eq, Reflect, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SensorStateClass {
+    /// The state represents a measurement in present time
+    Measurement,
+    /// The state represents a total amount that can both increase and decrease, resets are
+    /// tracked with `last_reset`
+    Total,
+    /// The state represents a monotonically increasing total, a decrease is a reset
+    TotalIncreasing,
 }
 
 #[cfg(test)]
@@ -1,15 +1,13 @@
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::*;
-use bevy_hierarchy::BuildChildren;
 use bevy_reflect::Reflect;
-use chrono::{DateTime, Utc};
-use log::debug;
-use std::str::FromStr;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
 
 mod constant;
 pub use constant::*;
 use skep_core::{
-    device::Device,
+    constants::STATE_UNKNOWN,
     typing::{SetupConfigEntry, ValueType},
 };
 
@@ -17,20 +15,25 @@ pub struct SkepSensorPlugin;
 
 impl Plugin for SkepSensorPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<Sensor>().observe(create_or_update);
+        app.register_type::<Sensor>();
     }
 }
 
-#[derive(Debug, Component, Default, Reflect)]
+const PAYLOAD_NONE: &str = "None";
+
+#[derive(Debug, Component, Default, Reflect, Deserialize)]
+#[serde(default)]
 pub struct Sensor {
     pub device_class: Option<SensorDeviceClass>,
     #[reflect(ignore)]
+    #[serde(skip)]
     pub last_reset: Option<DateTime<Utc>>,
     pub native_unit_of_measurement: Option<String>,
     #[reflect(ignore)]
+    #[serde(skip)]
     pub native_value: Option<ValueType>,
     pub options: Option<Vec<String>>,
-    pub state_class: Option<String>,
+    pub state_class: Option<SensorStateClass>,
     pub suggested_display_precision: Option<i32>,
     pub suggested_unit_of_measurement: Option<String>,
     pub unit_of_measurement: Option<String>,
@@ -41,23 +44,165 @@ impl Sensor {
         if event.component != "sensor" {
             return Err(anyhow::anyhow!("Invalid component"));
         }
-        let mut sensor = Sensor::default();
-        // sensor.device_class =
-        // SensorDeviceClass::from_str(&event.payload.get("device_class")?).ok();
+        let mut sensor = serde_json::from_value::<Sensor>(event.payload)?;
+        if sensor.native_unit_of_measurement.is_none() {
+            sensor.native_unit_of_measurement = sensor.unit_of_measurement.clone();
+        }
+        match (&sensor.device_class, &sensor.options) {
+            (Some(SensorDeviceClass::Enum), None) => {
+                return Err(anyhow::anyhow!("An enum sensor requires options"));
+            }
+            // Options without a device class make an enum sensor
+            (Some(device_class), Some(_)) if device_class != &SensorDeviceClass::Enum => {
+                return Err(anyhow::anyhow!("Options are only allowed for enum sensors"));
+            }
+            _ => {}
+        }
         Ok(sensor)
     }
+
+    /// Whether the state must be a number, either from the device class or because a unit or
+    /// state class is set. A sensor with options is an enum and never numeric.
+    pub fn is_numeric(&self) -> bool {
+        match &self.device_class {
+            Some(device_class) => device_class.is_numeric(),
+            None => {
+                self.options.is_none()
+                    && (self.native_unit_of_measurement.is_some() || self.state_class.is_some())
+            }
+        }
+    }
+
+    /// Parse a rendered value into the native value of the sensor, `None` for an unknown state.
+    pub fn parse_native_value(&self, value: &str) -> anyhow::Result<Option<ValueType>> {
+        let value = value.trim();
+        if value.is_empty() || value == PAYLOAD_NONE {
+            return Ok(None);
+        }
+        if let Some(options) = &self.options {
+            if !options.iter().any(|option| option == value) {
+                return Err(anyhow::anyhow!("{} is not one of {:?}", value, options));
+            }
+            return Ok(Some(ValueType::String(value.to_string())));
+        }
+        let native_value = match &self.device_class {
+            Some(SensorDeviceClass::Date) => ValueType::Date(
+                NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .or_else(|_| DateTime::parse_from_rfc3339(value).map(|d| d.date_naive()))?,
+            ),
+            Some(SensorDeviceClass::Timestamp) => {
+                ValueType::DateTime(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc))
+            }
+            _ if self.is_numeric() => match value.parse::<i64>() {
+                Ok(value) => ValueType::Int(value),
+                Err(_) => {
+                    let value = value.parse::<f64>()?;
+                    if !value.is_finite() {
+                        return Err(anyhow::anyhow!("{} is not a finite number", value));
+                    }
+                    ValueType::Float(value)
+                }
+            },
+            _ => ValueType::String(value.to_string()),
+        };
+        Ok(Some(native_value))
+    }
+
+    /// The state string of the native value, rounded to `suggested_display_precision`.
+    pub fn state(&self) -> String {
+        match (&self.native_value, self.suggested_display_precision) {
+            (None, _) => STATE_UNKNOWN.to_string(),
+            (Some(ValueType::Float(value)), Some(precision)) => {
+                format!("{:.*}", precision.max(0) as usize, value)
+            }
+            (Some(value), _) => value.to_string(),
+        }
+    }
 }
 
-fn create_or_update(
-    trigger: Trigger<SetupConfigEntry>,
-    device_query: Query<&Device>,
-    mut commands: Commands,
-) {
-    if let Ok(sensor) = Sensor::from_config(trigger.event().clone()) {
-        // debug!("sensor create_or_update {:?}", sensor);
-        // let sensor_entity = commands.spawn_empty().insert(sensor).id();
-        // if let Ok(_device) = device_query.get(trigger.entity()) {
-        //     commands.entity(trigger.entity()).add_child(sensor_entity);
-        // }
+#[cfg(test)]
+mod test {
+    use crate::{Sensor, SensorDeviceClass, SensorStateClass};
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use serde_json::json;
+    use skep_core::typing::{SetupConfigEntry, ValueType};
+
+    fn sensor(payload: serde_json::Value) -> anyhow::Result<Sensor> {
+        Sensor::from_config(SetupConfigEntry {
+            component: "sensor".to_string(),
+            object_id: "test".to_string(),
+            payload,
+        })
+    }
+
+    #[test]
+    fn test_sensor_native_value() {
+        let temperature = sensor(json!({
+            "device_class": "temperature",
+            "unit_of_measurement": "°C",
+            "state_class": "measurement",
+            "suggested_display_precision": 1
+        }))
+        .unwrap();
+        assert_eq!(
+            temperature.native_unit_of_measurement.as_deref(),
+            Some("°C")
+        );
+        assert_eq!(temperature.state_class, Some(SensorStateClass::Measurement));
+        assert_eq!(
+            temperature.parse_native_value("21").unwrap(),
+            Some(ValueType::Int(21))
+        );
+        assert!(temperature.parse_native_value("warm").is_err());
+        assert!(temperature.parse_native_value("NaN").is_err());
+        assert_eq!(temperature.parse_native_value("None").unwrap(), None);
+
+        let mut temperature = temperature;
+        temperature.native_value = temperature.parse_native_value("21.456").unwrap();
+        assert_eq!(temperature.state(), "21.5");
+
+        let timestamp = sensor(json!({"device_class": "timestamp"})).unwrap();
+        assert_eq!(
+            timestamp
+                .parse_native_value("2024-09-01T12:00:00+02:00")
+                .unwrap(),
+            Some(ValueType::DateTime(
+                Utc.with_ymd_and_hms(2024, 9, 1, 10, 0, 0).unwrap()
+            ))
+        );
+        assert!(timestamp.parse_native_value("2024-09-01 12:00").is_err());
+
+        let date = sensor(json!({"device_class": "date"})).unwrap();
+        assert_eq!(
+            date.parse_native_value("2024-09-01").unwrap(),
+            Some(ValueType::Date(
+                NaiveDate::from_ymd_opt(2024, 9, 1).unwrap()
+            ))
+        );
+
+        let mode = sensor(json!({"device_class": "enum", "options": ["eco", "comfort"]})).unwrap();
+        assert_eq!(mode.device_class, Some(SensorDeviceClass::Enum));
+        assert_eq!(
+            mode.parse_native_value("eco").unwrap(),
+            Some(ValueType::String("eco".to_string()))
+        );
+        assert!(mode.parse_native_value("boost").is_err());
+        assert!(sensor(json!({"device_class": "enum"})).is_err());
+        assert!(sensor(json!({"device_class": "power", "options": ["a"]})).is_err());
+
+        let mode = sensor(json!({"options": ["eco", "comfort"]})).unwrap();
+        assert_eq!(mode.device_class, None);
+        assert_eq!(
+            mode.parse_native_value("comfort").unwrap(),
+            Some(ValueType::String("comfort".to_string()))
+        );
+        assert!(mode.parse_native_value("boost").is_err());
+
+        let text = sensor(json!({})).unwrap();
+        assert!(!text.is_numeric());
+        assert_eq!(
+            text.parse_native_value("hello").unwrap(),
+            Some(ValueType::String("hello".to_string()))
+        );
     }
 }